@@ -0,0 +1,293 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{world_to_cell, TextInput, Tile};
+
+// neighbour bits, orthogonal first so a 4-neighbour mask is just the low nibble
+const NORTH: u8 = 1;
+const EAST: u8 = 2;
+const SOUTH: u8 = 4;
+const WEST: u8 = 8;
+const NORTH_EAST: u8 = 16;
+const SOUTH_EAST: u8 = 32;
+const SOUTH_WEST: u8 = 64;
+const NORTH_WEST: u8 = 128;
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Neighbourhood {
+    Four,
+    Eight,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct TerrainRule {
+    pub mask: u8,
+    pub index: usize,
+}
+
+/// One terrain brush, read from `<tileset>.terrain.json` next to the tileset image.
+/// In `eight` mode a diagonal bit only counts when both orthogonal neighbours
+/// touching it are set, so a full table needs 47 entries instead of 256.
+#[derive(Deserialize, Clone, Debug)]
+pub struct TerrainRuleSet {
+    pub name: String,
+    pub neighbourhood: Neighbourhood,
+    pub default_index: usize,
+    pub rules: Vec<TerrainRule>,
+}
+
+impl TerrainRuleSet {
+    pub fn mask(&self, cell: IVec2, filled: impl Fn(IVec2) -> bool) -> u8 {
+        let mut mask = 0;
+        let n = filled(cell + IVec2::Y);
+        let e = filled(cell + IVec2::X);
+        let s = filled(cell - IVec2::Y);
+        let w = filled(cell - IVec2::X);
+
+        for (set, bit) in [(n, NORTH), (e, EAST), (s, SOUTH), (w, WEST)] {
+            if set {
+                mask |= bit;
+            }
+        }
+
+        if self.neighbourhood == Neighbourhood::Eight {
+            let diagonals = [
+                (n && e, IVec2::new(1, 1), NORTH_EAST),
+                (s && e, IVec2::new(1, -1), SOUTH_EAST),
+                (s && w, IVec2::new(-1, -1), SOUTH_WEST),
+                (n && w, IVec2::new(-1, 1), NORTH_WEST),
+            ];
+            for (sides, offset, bit) in diagonals {
+                if sides && filled(cell + offset) {
+                    mask |= bit;
+                }
+            }
+        }
+        mask
+    }
+
+    pub fn index_for(&self, mask: u8) -> usize {
+        self.rules
+            .iter()
+            .find(|rule| rule.mask == mask)
+            .map(|rule| rule.index)
+            .unwrap_or(self.default_index)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct TerrainBrushes {
+    // the tileset the rules were read for
+    pub tileset: String,
+    pub sets: Vec<TerrainRuleSet>,
+    pub active: usize,
+}
+
+impl TerrainBrushes {
+    pub fn active(&self) -> Option<&TerrainRuleSet> {
+        self.sets.get(self.active)
+    }
+
+    // follows the tiles into a resized tileset, the rules file itself is left as the artist
+    // wrote it and indices that were cut off stay as they are
+    pub fn remap(&mut self, remap: impl Fn(usize) -> Option<usize>) {
+        let mut changed = false;
+        for set in &mut self.sets {
+            let indices = std::iter::once(&mut set.default_index)
+                .chain(set.rules.iter_mut().map(|rule| &mut rule.index));
+            for index in indices {
+                match remap(*index) {
+                    Some(to) => {
+                        changed |= to != *index;
+                        *index = to;
+                    }
+                    None => warn!(
+                        "terrain {} uses tile {index}, which is outside the tileset",
+                        set.name
+                    ),
+                }
+            }
+        }
+        if changed {
+            warn!(
+                "terrain rules moved with the resized tileset, update {}",
+                terrain_path(&self.tileset).display()
+            );
+        }
+    }
+}
+
+// which terrain brush painted a tile, tiles only connect to their own terrain
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub struct Terrain(pub usize);
+
+#[derive(Event, Clone, Copy)]
+pub struct TerrainChanged(pub IVec2);
+
+fn terrain_path(tileset: &str) -> PathBuf {
+    Path::new("assets")
+        .join(tileset)
+        .with_extension("terrain.json")
+}
+
+// (re)reads the rules whenever a level switches to another tileset
+pub fn load_terrain_rules(mut brushes: ResMut<TerrainBrushes>, text_res: Res<TextInput>) {
+    if brushes.tileset == text_res.0 {
+        return;
+    }
+    *brushes = TerrainBrushes {
+        tileset: text_res.0.clone(),
+        ..default()
+    };
+
+    let path = terrain_path(&text_res.0);
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        info!("no terrain rules at {}", path.display());
+        return;
+    };
+    match serde_json::from_str::<Vec<TerrainRuleSet>>(&json) {
+        Ok(sets) => brushes.sets = sets,
+        Err(err) => warn!("failed to parse {}: {err}", path.display()),
+    }
+}
+
+pub fn cycle_terrain_brush(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut brushes: ResMut<TerrainBrushes>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyT) || brushes.sets.is_empty() {
+        return;
+    }
+    brushes.active = (brushes.active + 1) % brushes.sets.len();
+    info!("terrain brush: {}", brushes.sets[brushes.active].name);
+}
+
+// recompute the atlas index of every terrain tile touching a changed cell
+pub fn update_autotiles(
+    mut changed_r: EventReader<TerrainChanged>,
+    brushes: Res<TerrainBrushes>,
+    mut tile_q: Query<(&Transform, &Terrain, &mut Tile, &mut TextureAtlas)>,
+) {
+    let mut dirty = HashSet::new();
+    for TerrainChanged(cell) in changed_r.read() {
+        for x in -1..=1 {
+            for y in -1..=1 {
+                dirty.insert(*cell + IVec2::new(x, y));
+            }
+        }
+    }
+    if dirty.is_empty() {
+        return;
+    }
+
+    let cells: HashMap<IVec2, Terrain> = tile_q
+        .iter()
+        .map(|(transform, terrain, ..)| (world_to_cell(transform.translation), *terrain))
+        .collect();
+
+    for (transform, terrain, mut tile, mut atlas) in &mut tile_q {
+        let cell = world_to_cell(transform.translation);
+        if !dirty.contains(&cell) {
+            continue;
+        }
+        let Some(rule_set) = brushes.sets.get(terrain.0) else {
+            continue;
+        };
+        let mask = rule_set.mask(cell, |c| cells.get(&c) == Some(terrain));
+        let index = rule_set.index_for(mask);
+        if tile.0 != index {
+            tile.0 = index;
+            atlas.index = index;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule_set(neighbourhood: Neighbourhood) -> TerrainRuleSet {
+        TerrainRuleSet {
+            name: "ground".to_string(),
+            neighbourhood,
+            default_index: 0,
+            rules: vec![
+                TerrainRule {
+                    mask: NORTH | SOUTH,
+                    index: 5,
+                },
+                TerrainRule {
+                    mask: NORTH | EAST | NORTH_EAST,
+                    index: 9,
+                },
+            ],
+        }
+    }
+
+    fn filled(cells: &[IVec2]) -> impl Fn(IVec2) -> bool + '_ {
+        move |cell| cells.contains(&cell)
+    }
+
+    #[test]
+    fn four_neighbourhood_ignores_diagonals() {
+        let cells = [IVec2::new(0, 1), IVec2::new(1, 0), IVec2::new(1, 1)];
+        let mask = rule_set(Neighbourhood::Four).mask(IVec2::ZERO, filled(&cells));
+        assert_eq!(mask, NORTH | EAST);
+    }
+
+    #[test]
+    fn diagonal_needs_both_orthogonal_neighbours() {
+        let rules = rule_set(Neighbourhood::Eight);
+        let corner = [IVec2::new(0, 1), IVec2::new(1, 0), IVec2::new(1, 1)];
+        assert_eq!(
+            rules.mask(IVec2::ZERO, filled(&corner)),
+            NORTH | EAST | NORTH_EAST
+        );
+
+        // without the east neighbour the north-east diagonal doesn't count
+        let open = [IVec2::new(0, 1), IVec2::new(1, 1), IVec2::new(-1, -1)];
+        assert_eq!(rules.mask(IVec2::ZERO, filled(&open)), NORTH);
+    }
+
+    #[test]
+    fn surrounded_cell_sets_every_bit() {
+        let mut cells = Vec::new();
+        for x in -1..=1 {
+            for y in -1..=1 {
+                cells.push(IVec2::new(x, y));
+            }
+        }
+        let mask = rule_set(Neighbourhood::Eight).mask(IVec2::ZERO, filled(&cells));
+        assert_eq!(mask, u8::MAX);
+    }
+
+    #[test]
+    fn index_for_falls_back_to_default() {
+        let rules = rule_set(Neighbourhood::Eight);
+        assert_eq!(rules.index_for(NORTH | SOUTH), 5);
+        assert_eq!(rules.index_for(NORTH | EAST | NORTH_EAST), 9);
+        assert_eq!(rules.index_for(NORTH | EAST), 0);
+        assert_eq!(rules.index_for(0), 0);
+    }
+
+    #[test]
+    fn remap_keeps_indices_that_no_longer_fit() {
+        let mut brushes = TerrainBrushes {
+            sets: vec![rule_set(Neighbourhood::Eight)],
+            ..default()
+        };
+        // 4 columns grown to 8, the third row is cut off
+        let remap = |index: usize| (index < 8).then(|| index / 4 * 8 + index % 4);
+        brushes.remap(remap);
+        let rules = &brushes.sets[0];
+        assert_eq!(rules.default_index, 0);
+        assert_eq!(rules.index_for(NORTH | SOUTH), 9);
+        assert_eq!(rules.index_for(NORTH | EAST | NORTH_EAST), 9);
+    }
+}
@@ -0,0 +1,220 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bevy::prelude::*;
+
+// what the level file gets besides per-tile data, outlines are opt-in
+#[derive(Resource, Default)]
+pub struct CollisionExport {
+    pub outlines: bool,
+}
+
+pub fn toggle_outline_export(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut export: ResMut<CollisionExport>,
+) {
+    if keyboard_input.pressed(KeyCode::ControlLeft) && keyboard_input.just_pressed(KeyCode::KeyE) {
+        export.outlines = !export.outlines;
+        info!("collision outlines on export: {}", export.outlines);
+    }
+}
+
+/// Covers the solid cells with as few axis-aligned rectangles as a greedy sweep finds.
+/// Rows are scanned bottom to top; each rectangle grows right as far as it can, then up
+/// while the whole span above is still solid and uncovered. Rects are in cell units,
+/// `max` exclusive.
+pub fn merge_rects(cells: &HashSet<IVec2>) -> Vec<IRect> {
+    let mut sorted: Vec<IVec2> = cells.iter().copied().collect();
+    sorted.sort_by_key(|cell| (cell.y, cell.x));
+
+    let mut covered = HashSet::new();
+    let mut rects = Vec::new();
+    for start in sorted {
+        if covered.contains(&start) {
+            continue;
+        }
+        let free = |cell: IVec2| cells.contains(&cell) && !covered.contains(&cell);
+
+        let mut width = 1;
+        while free(start + IVec2::new(width, 0)) {
+            width += 1;
+        }
+        let mut height = 1;
+        while (0..width).all(|x| free(start + IVec2::new(x, height))) {
+            height += 1;
+        }
+
+        for y in 0..height {
+            for x in 0..width {
+                covered.insert(start + IVec2::new(x, y));
+            }
+        }
+        rects.push(IRect::from_corners(
+            start,
+            start + IVec2::new(width, height),
+        ));
+    }
+    rects
+}
+
+/// Traces the boundary of the solid cells into closed polylines of cell corners, solid
+/// side on the left (counter-clockwise around solids, clockwise around holes). Points
+/// along straight runs are dropped, the last point connects back to the first.
+pub fn outlines(cells: &HashSet<IVec2>) -> Vec<Vec<IVec2>> {
+    // boundary edges keyed by their start corner, BTreeMap keeps the output stable
+    let mut edges: BTreeMap<(i32, i32), Vec<(i32, i32)>> = BTreeMap::new();
+    let mut add_edge = |from: IVec2, to: IVec2| {
+        edges
+            .entry((from.x, from.y))
+            .or_default()
+            .push((to.x, to.y));
+    };
+    for cell in cells {
+        let (x, y) = (cell.x, cell.y);
+        if !cells.contains(&IVec2::new(x, y - 1)) {
+            add_edge(IVec2::new(x, y), IVec2::new(x + 1, y));
+        }
+        if !cells.contains(&IVec2::new(x + 1, y)) {
+            add_edge(IVec2::new(x + 1, y), IVec2::new(x + 1, y + 1));
+        }
+        if !cells.contains(&IVec2::new(x, y + 1)) {
+            add_edge(IVec2::new(x + 1, y + 1), IVec2::new(x, y + 1));
+        }
+        if !cells.contains(&IVec2::new(x - 1, y)) {
+            add_edge(IVec2::new(x, y + 1), IVec2::new(x, y));
+        }
+    }
+
+    let mut loops = Vec::new();
+    while let Some((&start, _)) = edges.iter().next() {
+        let mut points = vec![IVec2::new(start.0, start.1)];
+        let mut current = start;
+        let mut heading: Option<IVec2> = None;
+        while let Some(ends) = edges.get_mut(&current) {
+            // a corner where two loops touch diagonally has two ways out, turning left
+            // keeps each loop around its own cells whatever order the edges came in
+            let from = IVec2::new(current.0, current.1);
+            let pick = (0..ends.len())
+                .min_by_key(|&i| {
+                    let to = IVec2::new(ends[i].0, ends[i].1);
+                    (
+                        heading.map_or(0, |heading| turn(heading, to - from)),
+                        ends[i],
+                    )
+                })
+                .expect("edge lists are never left empty");
+            let next = ends.swap_remove(pick);
+            heading = Some(IVec2::new(next.0, next.1) - from);
+            if ends.is_empty() {
+                edges.remove(&current);
+            }
+            if next == start {
+                break;
+            }
+            points.push(IVec2::new(next.0, next.1));
+            current = next;
+        }
+        loops.push(drop_collinear(points));
+    }
+    loops
+}
+
+// how sharply `to` turns away from `heading`: left, straight on, right, back
+fn turn(heading: IVec2, to: IVec2) -> u8 {
+    match to {
+        _ if to == heading.perp() => 0,
+        _ if to == heading => 1,
+        _ if to == -heading.perp() => 2,
+        _ => 3,
+    }
+}
+
+fn drop_collinear(points: Vec<IVec2>) -> Vec<IVec2> {
+    let len = points.len();
+    (0..len)
+        .filter(|&i| {
+            let prev = points[(i + len - 1) % len];
+            let next = points[(i + 1) % len];
+            let point = points[i];
+            (point - prev).signum() != (next - point).signum()
+        })
+        .map(|i| points[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cells(cells: &[(i32, i32)]) -> HashSet<IVec2> {
+        cells.iter().map(|&(x, y)| IVec2::new(x, y)).collect()
+    }
+
+    fn points(points: &[(i32, i32)]) -> Vec<IVec2> {
+        points.iter().map(|&(x, y)| IVec2::new(x, y)).collect()
+    }
+
+    #[test]
+    fn l_shape() {
+        let cells = cells(&[(0, 0), (1, 0), (0, 1)]);
+        assert_eq!(
+            merge_rects(&cells),
+            vec![IRect::new(0, 0, 2, 1), IRect::new(0, 1, 1, 2)]
+        );
+        assert_eq!(
+            outlines(&cells),
+            vec![points(&[(0, 0), (2, 0), (2, 1), (1, 1), (1, 2), (0, 2)])]
+        );
+    }
+
+    #[test]
+    fn ring_around_a_hole() {
+        let cells = cells(&[
+            (0, 0),
+            (1, 0),
+            (2, 0),
+            (0, 1),
+            (2, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+        ]);
+        assert_eq!(
+            merge_rects(&cells),
+            vec![
+                IRect::new(0, 0, 3, 1),
+                IRect::new(0, 1, 1, 3),
+                IRect::new(2, 1, 3, 3),
+                IRect::new(1, 2, 2, 3),
+            ]
+        );
+        // the hole runs clockwise so the solid stays on the left
+        assert_eq!(
+            outlines(&cells),
+            vec![
+                points(&[(0, 0), (3, 0), (3, 3), (0, 3)]),
+                points(&[(1, 1), (1, 2), (2, 2), (2, 1)]),
+            ]
+        );
+    }
+
+    #[test]
+    fn diagonal_touch_keeps_loops_apart() {
+        let rising = cells(&[(0, 0), (1, 1)]);
+        assert_eq!(
+            outlines(&rising),
+            vec![
+                points(&[(0, 0), (1, 0), (1, 1), (0, 1)]),
+                points(&[(1, 1), (2, 1), (2, 2), (1, 2)]),
+            ]
+        );
+
+        let falling = cells(&[(0, 1), (1, 0)]);
+        assert_eq!(
+            outlines(&falling),
+            vec![
+                points(&[(0, 1), (1, 1), (1, 2), (0, 2)]),
+                points(&[(1, 0), (2, 0), (2, 1), (1, 1)]),
+            ]
+        );
+    }
+}
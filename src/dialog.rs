@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+
+use bevy::{prelude::*, window::WindowCloseRequested};
+
+use crate::{
+    level::{CurrentLevel, OpenLevel, SaveLevel},
+    AppState, BORDER_COLOR, BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR, PRESSED_BORDER,
+};
+
+#[derive(Clone, PartialEq, Eq)]
+pub enum DialogAction {
+    Exit,
+    Clear(ClearScope),
+    // by path, the project's level list can change while the dialog is up
+    Open(PathBuf),
+}
+
+// action waiting on the unsaved changes dialog
+#[derive(Resource, Default)]
+pub struct PendingAction(pub Option<DialogAction>);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ClearScope {
+    All,
+    Tiles,
+    Hazards,
+    Mobs,
+    Entities,
+    Regions,
+}
+
+impl ClearScope {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ClearScope::All => "the canvas",
+            ClearScope::Tiles => "all tiles",
+            ClearScope::Hazards => "all hazards",
+            ClearScope::Mobs => "all mobs",
+            ClearScope::Entities => "all custom entities",
+            ClearScope::Regions => "all regions",
+        }
+    }
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct ClearCanvas(pub ClearScope);
+
+#[derive(Event, Clone, Copy)]
+pub struct ResolvePending {
+    discard: bool,
+}
+
+#[derive(Component)]
+pub struct UnsavedDialog;
+
+#[derive(Component, Clone, Copy)]
+pub enum DialogButton {
+    Save,
+    Discard,
+    Cancel,
+}
+
+pub fn handle_close_requested(
+    mut close_r: EventReader<WindowCloseRequested>,
+    current_level: Res<CurrentLevel>,
+    mut pending: ResMut<PendingAction>,
+    mut exit_w: EventWriter<AppExit>,
+    app_state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if close_r.read().count() == 0 {
+        return;
+    }
+
+    match current_level.dirty {
+        true => {
+            // the dialog's buttons only work in the editor, leave a playtest or the world view
+            if matches!(app_state.get(), AppState::Playtest | AppState::WorldView) {
+                next_state.set(AppState::InLevelEdit);
+            }
+            pending.0 = Some(DialogAction::Exit);
+        }
+        false => {
+            exit_w.send(AppExit::Success);
+        }
+    }
+}
+
+pub fn sync_unsaved_dialog(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    pending: Res<PendingAction>,
+    current_level: Res<CurrentLevel>,
+    dialog_q: Query<Entity, With<UnsavedDialog>>,
+) {
+    for ent in &dialog_q {
+        commands.entity(ent).despawn_recursive();
+    }
+    let Some(action) = &pending.0 else {
+        return;
+    };
+
+    let text_style = TextStyle {
+        font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+        font_size: 25.0,
+        color: HOVER_BORDER,
+    };
+    let message = match action {
+        DialogAction::Exit => "Unsaved changes. Save before closing?".to_string(),
+        DialogAction::Clear(scope) => {
+            format!("Unsaved changes. Save before clearing {}?", scope.label())
+        }
+        DialogAction::Open(path) if *path == current_level.path => {
+            "Unsaved changes. Save before reopening the level?".to_string()
+        }
+        DialogAction::Open(_) => "Unsaved changes. Save before opening another level?".to_string(),
+    };
+    let button = ButtonBundle {
+        style: Style {
+            width: Val::Px(100.0),
+            height: Val::Px(40.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        border_color: BorderColor(BORDER_COLOR),
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                align_self: AlignSelf::Center,
+                justify_self: JustifySelf::Center,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(12.0),
+                padding: UiRect::all(Val::Px(16.0)),
+                border: UiRect::all(Val::Px(2.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR.with_alpha(0.9)),
+            border_color: BorderColor(BORDER_COLOR),
+            z_index: ZIndex::Global(10),
+            ..default()
+        })
+        .insert(UnsavedDialog)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(message, text_style.clone()));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (label, choice) in [
+                        ("Save", DialogButton::Save),
+                        ("Discard", DialogButton::Discard),
+                        ("Cancel", DialogButton::Cancel),
+                    ] {
+                        row.spawn(button.clone())
+                            .with_children(|p| {
+                                p.spawn(TextBundle::from_section(label, text_style.clone()));
+                            })
+                            .insert(choice);
+                    }
+                });
+        });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn unsaved_dialog_interaction(
+    mut interaction_q: Query<
+        (&Interaction, &mut BorderColor, &DialogButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut pending: ResMut<PendingAction>,
+    mut save_w: EventWriter<SaveLevel>,
+    mut resolve_w: EventWriter<ResolvePending>,
+) {
+    for (interaction, mut color, choice) in &mut interaction_q {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = BorderColor(PRESSED_BORDER);
+                match choice {
+                    DialogButton::Save => {
+                        save_w.send(SaveLevel);
+                        resolve_w.send(ResolvePending { discard: false });
+                    }
+                    DialogButton::Discard => {
+                        resolve_w.send(ResolvePending { discard: true });
+                    }
+                    DialogButton::Cancel => pending.0 = None,
+                }
+            }
+            Interaction::Hovered => {
+                *color = BorderColor(HOVER_BORDER);
+            }
+            Interaction::None => {
+                *color = BorderColor(BORDER_COLOR);
+            }
+        }
+    }
+}
+
+// runs after save_level so a failed save keeps the dialog open
+pub fn resolve_pending_action(
+    mut resolve_r: EventReader<ResolvePending>,
+    current_level: Res<CurrentLevel>,
+    mut pending: ResMut<PendingAction>,
+    mut exit_w: EventWriter<AppExit>,
+    mut clear_w: EventWriter<ClearCanvas>,
+    mut open_w: EventWriter<OpenLevel>,
+) {
+    let Some(resolve) = resolve_r.read().last().copied() else {
+        return;
+    };
+    if !resolve.discard && current_level.dirty {
+        return;
+    }
+
+    match pending.0.take() {
+        Some(DialogAction::Exit) => {
+            exit_w.send(AppExit::Success);
+        }
+        Some(DialogAction::Clear(scope)) => {
+            clear_w.send(ClearCanvas(scope));
+        }
+        Some(DialogAction::Open(path)) => {
+            open_w.send(OpenLevel(path));
+        }
+        None => {}
+    }
+}
@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, path::Path};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{ColliderBundle, LevelEntity};
+
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    String,
+    Int,
+    Float,
+    Bool,
+    // a link to another placed entity, stored in CustomEntity::links
+    Ref,
+}
+
+impl FieldType {
+    // the type of a stored value, so edits keep it
+    pub fn of(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) => FieldType::Bool,
+            Value::Number(number) if number.is_i64() => FieldType::Int,
+            Value::Number(_) => FieldType::Float,
+            _ => FieldType::String,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            FieldType::String => "string",
+            FieldType::Int => "int",
+            FieldType::Float => "float",
+            FieldType::Bool => "bool",
+            FieldType::Ref => "ref",
+        }
+    }
+
+    // parses typed text into a value of this type
+    pub fn parse(&self, text: &str) -> Result<Value, String> {
+        let text = text.trim();
+        match self {
+            FieldType::String => Ok(Value::from(text)),
+            FieldType::Int => text
+                .parse::<i64>()
+                .map(Value::from)
+                .map_err(|_| format!("{text} is not a whole number")),
+            FieldType::Float => text
+                .parse::<f64>()
+                .map(Value::from)
+                .map_err(|_| format!("{text} is not a number")),
+            FieldType::Bool => text
+                .parse::<bool>()
+                .map(Value::from)
+                .map_err(|_| format!("{text} is not true or false")),
+            FieldType::Ref => Err("links are set with the link tool".to_string()),
+        }
+    }
+
+    fn default_value(&self) -> Value {
+        match self {
+            FieldType::String => Value::from(""),
+            FieldType::Int => Value::from(0),
+            FieldType::Float => Value::from(0.0),
+            FieldType::Bool => Value::from(false),
+            FieldType::Ref => Value::Null,
+        }
+    }
+
+    // whether a value read from a level file fits this type
+    fn accepts(&self, value: &Value) -> bool {
+        match self {
+            FieldType::String => value.is_string(),
+            FieldType::Int => value.is_i64(),
+            FieldType::Float => value.is_number(),
+            FieldType::Bool => value.is_boolean(),
+            FieldType::Ref => value.is_u64() || value.is_null(),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone)]
+pub struct FieldDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub field_type: FieldType,
+    #[serde(default)]
+    pub default: Option<Value>,
+}
+
+impl FieldDef {
+    fn default_value(&self) -> Value {
+        match &self.default {
+            Some(value) if self.field_type.accepts(value) => value.clone(),
+            _ => self.field_type.default_value(),
+        }
+    }
+}
+
+/// A placeable entity kind such as a door, key, checkpoint, exit or trigger, read from
+/// `assets/entities.json`. Each kind gets its own toolbar button and carries the typed
+/// `fields` listed here.
+#[derive(Deserialize, Clone)]
+pub struct EntityType {
+    pub name: String,
+    #[serde(default)]
+    pub atlas_index: Option<usize>,
+    #[serde(default = "default_entity_color")]
+    pub color: [f32; 3],
+    #[serde(default = "default_entity_size")]
+    pub size: [f32; 2],
+    #[serde(default)]
+    pub fields: Vec<FieldDef>,
+}
+
+fn default_entity_color() -> [f32; 3] {
+    [0.2, 0.6, 0.9]
+}
+
+fn default_entity_size() -> [f32; 2] {
+    [24.0, 24.0]
+}
+
+impl EntityType {
+    pub fn tint(&self) -> Color {
+        let [r, g, b] = self.color;
+        Color::srgb(r, g, b)
+    }
+
+    pub fn instance(&self) -> CustomEntity {
+        self.instance_with(&BTreeMap::new(), &BTreeMap::new())
+    }
+
+    // fills in schema defaults for fields a saved entity doesn't have or has the wrong type for
+    pub fn instance_with(
+        &self,
+        saved: &BTreeMap<String, Value>,
+        saved_links: &BTreeMap<String, u64>,
+    ) -> CustomEntity {
+        let (refs, values): (Vec<&FieldDef>, Vec<&FieldDef>) = self
+            .fields
+            .iter()
+            .partition(|field| field.field_type == FieldType::Ref);
+        let fields = values
+            .into_iter()
+            .map(|field| {
+                let value = match saved.get(&field.name) {
+                    Some(value) if field.field_type.accepts(value) => value.clone(),
+                    _ => field.default_value(),
+                };
+                (field.name.clone(), value)
+            })
+            .collect();
+        let links = refs
+            .into_iter()
+            .map(|field| (field.name.clone(), saved_links.get(&field.name).copied()))
+            .collect();
+        CustomEntity {
+            kind: self.name.clone(),
+            fields,
+            links,
+        }
+    }
+}
+
+// a placed instance of an EntityType
+#[derive(Component, Clone)]
+pub struct CustomEntity {
+    pub kind: String,
+    pub fields: BTreeMap<String, Value>,
+    // ref fields, holding the EntityId of the linked entity once set
+    pub links: BTreeMap<String, Option<u64>>,
+}
+
+#[derive(Resource, Default)]
+pub struct EntityRegistry {
+    pub types: Vec<EntityType>,
+}
+
+impl EntityRegistry {
+    pub fn get(&self, name: &str) -> Option<&EntityType> {
+        self.types
+            .iter()
+            .find(|entity_type| entity_type.name == name)
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.types
+            .iter()
+            .position(|entity_type| entity_type.name == name)
+    }
+
+    // the declared type of a field, None when the kind or field is no longer listed
+    pub fn field_type(&self, kind: &str, field: &str) -> Option<FieldType> {
+        self.get(kind)?
+            .fields
+            .iter()
+            .find(|def| def.name == field)
+            .map(|def| def.field_type)
+    }
+
+    // rebuilds a saved entity, keeping its fields as they are if its kind is no longer listed
+    pub fn restore(
+        &self,
+        kind: &str,
+        saved: &BTreeMap<String, Value>,
+        saved_links: &BTreeMap<String, u64>,
+    ) -> CustomEntity {
+        match self.get(kind) {
+            Some(entity_type) => entity_type.instance_with(saved, saved_links),
+            None => {
+                warn!("unknown entity type {kind}");
+                CustomEntity {
+                    kind: kind.to_string(),
+                    fields: saved.clone(),
+                    links: saved_links
+                        .iter()
+                        .map(|(name, id)| (name.clone(), Some(*id)))
+                        .collect(),
+                }
+            }
+        }
+    }
+}
+
+pub fn load_entity_registry(mut registry: ResMut<EntityRegistry>) {
+    let path = Path::new("assets").join("entities.json");
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        info!("no entity types at {}", path.display());
+        return;
+    };
+    match serde_json::from_str::<Vec<EntityType>>(&json) {
+        Ok(types) => registry.types = types,
+        Err(err) => warn!("failed to parse {}: {err}", path.display()),
+    }
+}
+
+// shows a field value the way it is typed back in
+pub fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+pub fn spawn_custom_entity(
+    commands: &mut Commands,
+    pos: Vec3,
+    entity: CustomEntity,
+    registry: &EntityRegistry,
+    texture: &Handle<Image>,
+    layout: &Handle<TextureAtlasLayout>,
+) -> Entity {
+    let (size, color, atlas_index) = match registry.get(&entity.kind) {
+        Some(entity_type) => match entity_type.atlas_index {
+            Some(index) => (Vec2::from(entity_type.size), Color::WHITE, Some(index)),
+            None => (Vec2::from(entity_type.size), entity_type.tint(), None),
+        },
+        None => {
+            let [r, g, b] = default_entity_color();
+            (Vec2::splat(24.0), Color::srgb(r, g, b), None)
+        }
+    };
+    let mut ent = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(pos),
+            ..default()
+        },
+        LevelEntity,
+    ));
+    if let Some(index) = atlas_index {
+        ent.insert((
+            texture.clone(),
+            TextureAtlas {
+                index,
+                layout: layout.clone(),
+            },
+        ));
+    }
+    ent.insert(ColliderBundle::new(pos, size, entity));
+    ent.id()
+}
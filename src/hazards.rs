@@ -0,0 +1,211 @@
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    idle_border, ClickAnd, Hazard, BORDER_COLOR, BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR,
+    PRESSED_BORDER,
+};
+
+/// A hazard behavior, read from `assets/hazards.json`. The kind is chosen separately from
+/// the atlas tile a hazard is drawn with, and hazards are tinted in `color` in the editor.
+#[derive(Deserialize, Clone)]
+pub struct HazardType {
+    pub name: String,
+    #[serde(default)]
+    pub damage: i32,
+    #[serde(default)]
+    pub knockback: f32,
+    #[serde(default)]
+    pub instant_kill: bool,
+    #[serde(default = "default_hazard_color")]
+    pub color: [f32; 3],
+}
+
+fn default_hazard_color() -> [f32; 3] {
+    [1.0, 0.55, 0.55]
+}
+
+impl HazardType {
+    pub fn tint(&self) -> Color {
+        let [r, g, b] = self.color;
+        Color::srgb(r, g, b)
+    }
+
+    // short description of the behavior for the status bar
+    pub fn summary(&self) -> String {
+        let mut summary = match self.instant_kill {
+            true => format!("{} kill", self.name),
+            false => format!("{} dmg {}", self.name, self.damage),
+        };
+        if self.knockback != 0.0 {
+            summary.push_str(&format!(" kb {}", self.knockback));
+        }
+        summary
+    }
+}
+
+// name of the HazardType a placed hazard behaves as
+#[derive(Component, Clone, Default)]
+pub struct HazardKind(pub String);
+
+#[derive(Resource)]
+pub struct HazardRegistry {
+    pub types: Vec<HazardType>,
+    pub selected: usize,
+}
+
+impl Default for HazardRegistry {
+    fn default() -> Self {
+        Self {
+            types: vec![HazardType {
+                name: "hazard".to_string(),
+                damage: 1,
+                knockback: 0.0,
+                instant_kill: false,
+                color: default_hazard_color(),
+            }],
+            selected: 0,
+        }
+    }
+}
+
+impl HazardRegistry {
+    pub fn get(&self, name: &str) -> &HazardType {
+        self.types
+            .iter()
+            .find(|hazard_type| hazard_type.name == name)
+            .unwrap_or(&self.types[0])
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.types
+            .iter()
+            .position(|hazard_type| hazard_type.name == name)
+    }
+
+    pub fn selected(&self) -> &HazardType {
+        &self.types[self.selected]
+    }
+}
+
+#[derive(Component)]
+pub struct HazardPalette;
+
+#[derive(Component)]
+pub struct HazardTypeButton(pub usize);
+
+pub fn load_hazard_registry(mut registry: ResMut<HazardRegistry>) {
+    let path = Path::new("assets").join("hazards.json");
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        info!("no hazard kinds at {}", path.display());
+        return;
+    };
+    match serde_json::from_str::<Vec<HazardType>>(&json) {
+        Ok(types) if !types.is_empty() => {
+            registry.types = types;
+            registry.selected = 0;
+        }
+        Ok(_) => warn!("{} lists no hazard kinds", path.display()),
+        Err(err) => warn!("failed to parse {}: {err}", path.display()),
+    }
+}
+
+// tints hazards by kind so they read differently from plain tiles
+#[allow(clippy::type_complexity)]
+pub fn tint_hazards(
+    registry: Res<HazardRegistry>,
+    mut hazard_q: Query<(&HazardKind, &mut Sprite), (With<Hazard>, Changed<HazardKind>)>,
+) {
+    for (kind, mut sprite) in &mut hazard_q {
+        sprite.color = registry.get(&kind.0).tint();
+    }
+}
+
+pub fn setup_hazard_palette(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<HazardRegistry>,
+) {
+    let button = ButtonBundle {
+        style: Style {
+            height: Val::Px(32.0),
+            padding: UiRect::horizontal(Val::Px(6.0)),
+            align_items: AlignItems::Center,
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        border_color: BorderColor(BORDER_COLOR),
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(88.0),
+                bottom: Val::Px(40.0),
+                column_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(HazardPalette)
+        .with_children(|parent| {
+            for (index, hazard_type) in registry.types.iter().enumerate() {
+                let text_style = TextStyle {
+                    font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: hazard_type.tint(),
+                };
+                parent
+                    .spawn(button.clone())
+                    .with_children(|p| {
+                        p.spawn(TextBundle::from_section(
+                            hazard_type.name.clone(),
+                            text_style,
+                        ));
+                    })
+                    .insert(HazardTypeButton(index));
+            }
+        });
+}
+
+pub fn toggle_hazard_palette(
+    state: Res<State<ClickAnd>>,
+    mut palette_q: Query<&mut Style, With<HazardPalette>>,
+) {
+    let display = match state.get() {
+        ClickAnd::DrawHazard => Display::Flex,
+        _ => Display::None,
+    };
+    for mut style in &mut palette_q {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+pub fn hazard_palette_interaction(
+    mut button_q: Query<(&Interaction, &mut BorderColor, &HazardTypeButton)>,
+    mut registry: ResMut<HazardRegistry>,
+) {
+    for (interaction, mut color, hazard_button) in &mut button_q {
+        if *interaction == Interaction::Pressed && registry.selected != hazard_button.0 {
+            registry.selected = hazard_button.0;
+        }
+        let border = match *interaction {
+            Interaction::Pressed => BorderColor(PRESSED_BORDER),
+            Interaction::Hovered => BorderColor(HOVER_BORDER),
+            Interaction::None => idle_border(hazard_button.0 == registry.selected),
+        };
+        if color.0 != border.0 {
+            *color = border;
+        }
+    }
+}
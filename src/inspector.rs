@@ -0,0 +1,911 @@
+use std::collections::HashSet;
+
+use bevy::{
+    ecs::query::QueryData,
+    input::{
+        keyboard::{Key, KeyboardInput},
+        ButtonState,
+    },
+    prelude::*,
+};
+
+use crate::{
+    autotile::{Terrain, TerrainChanged},
+    cell_to_world, cursor_over_ui,
+    entities::{display_value, CustomEntity, EntityRegistry, FieldType},
+    hazards::{HazardKind, HazardRegistry},
+    level::CurrentLevel,
+    level_properties::{level_property_fields, LevelBounds, LevelProperties},
+    links::{DanglingLinks, EntityId, PendingLink},
+    patrol::{Patrol, PatrolMode},
+    regions::Region,
+    screen_to_world,
+    tile_properties::{tile_property_fields, TileProperties},
+    world_to_cell, ClickAnd, ClickEvent, Collider, Facing, Hazard, LevelEntity, Mob, Player,
+    SelectedTile, Tile, BORDER_COLOR, BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR, PRESSED_BORDER,
+};
+
+#[derive(Resource, Default)]
+pub struct Selection(pub Option<Entity>);
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum FieldKey {
+    MobFacing,
+    MobSpeed,
+    MobHealth,
+    MobProperty(String),
+    NewMobProperty,
+    PatrolMode,
+    ClearWaypoints,
+    TileIndex,
+    HazardIndex,
+    HazardKind,
+    ColliderPos,
+    ColliderSize,
+    EntityField(String),
+    // a bool entity field, toggled on click
+    EntityFlag(String),
+    // a ref field, clicking it arms the link tool
+    EntityLink(String),
+    RegionKind,
+    TileCollision,
+    TileFriction,
+    TileTags,
+    BoundsX,
+    BoundsY,
+    BoundsWidth,
+    BoundsHeight,
+    Background,
+    ResizeAnchor,
+    ResizeCrop,
+    LevelName,
+    LevelAuthor,
+    LevelMusic,
+    LevelParTime,
+    LevelProperty(String),
+    NewLevelProperty,
+}
+
+impl FieldKey {
+    // toggles and actions apply on click instead of opening the field for typing
+    fn applies_on_click(&self) -> bool {
+        matches!(
+            self,
+            FieldKey::MobFacing
+                | FieldKey::PatrolMode
+                | FieldKey::ClearWaypoints
+                | FieldKey::HazardKind
+                | FieldKey::EntityFlag(_)
+                | FieldKey::EntityLink(_)
+                | FieldKey::RegionKind
+                | FieldKey::TileCollision
+                | FieldKey::ResizeAnchor
+                | FieldKey::ResizeCrop
+        )
+    }
+
+    // keys that edit the selected tile's entry in the tileset property table
+    fn is_tile_property(&self) -> bool {
+        matches!(
+            self,
+            FieldKey::TileCollision | FieldKey::TileFriction | FieldKey::TileTags
+        )
+    }
+
+    // keys of the level property panel
+    fn is_level_property(&self) -> bool {
+        matches!(
+            self,
+            FieldKey::BoundsX
+                | FieldKey::BoundsY
+                | FieldKey::BoundsWidth
+                | FieldKey::BoundsHeight
+                | FieldKey::Background
+                | FieldKey::ResizeAnchor
+                | FieldKey::ResizeCrop
+                | FieldKey::LevelName
+                | FieldKey::LevelAuthor
+                | FieldKey::LevelMusic
+                | FieldKey::LevelParTime
+                | FieldKey::LevelProperty(_)
+                | FieldKey::NewLevelProperty
+        )
+    }
+}
+
+// one inspector row, fields without a key are read-only
+pub struct Field {
+    pub key: Option<FieldKey>,
+    pub label: String,
+    pub value: String,
+}
+
+impl Field {
+    pub fn read_only(label: &str, value: impl ToString) -> Self {
+        Self {
+            key: None,
+            label: label.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    pub fn editable(key: FieldKey, label: &str, value: impl ToString) -> Self {
+        Self {
+            key: Some(key),
+            label: label.to_string(),
+            value: value.to_string(),
+        }
+    }
+}
+
+// field currently being typed into
+#[derive(Resource, Default)]
+pub struct FieldEdit {
+    pub field: Option<FieldKey>,
+    pub buffer: String,
+}
+
+pub fn is_editing(edit: Res<FieldEdit>) -> bool {
+    edit.field.is_some()
+}
+
+#[derive(Component)]
+pub struct InspectorPanel;
+
+#[derive(Component)]
+pub struct InspectorField(pub FieldKey);
+
+// the components the inspector shows and edits on the selected entity
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct Inspected {
+    transform: &'static mut Transform,
+    collider: Option<&'static mut Collider>,
+    atlas: Option<&'static mut TextureAtlas>,
+    tile: Option<&'static mut Tile>,
+    terrain: Option<&'static Terrain>,
+    hazard: Option<&'static mut Hazard>,
+    hazard_kind: Option<&'static mut HazardKind>,
+    mob: Option<&'static mut Mob>,
+    patrol: Option<&'static mut Patrol>,
+    custom: Option<&'static mut CustomEntity>,
+    id: Option<&'static EntityId>,
+    region: Option<&'static mut Region>,
+    dangling: Option<&'static DanglingLinks>,
+    is_player: Has<Player>,
+}
+
+fn entity_fields(entity: &InspectedReadOnlyItem, hazards: &HazardRegistry) -> (String, Vec<Field>) {
+    let (title, mut fields) = if let Some(mob) = entity.mob {
+        ("Mob".to_string(), mob_fields(mob, entity.patrol))
+    } else if let Some(custom) = entity.custom {
+        let mut fields: Vec<Field> = entity
+            .id
+            .map(|id| Field::read_only("id", format!("#{}", id.0)))
+            .into_iter()
+            .collect();
+        fields.extend(custom.fields.iter().map(|(name, value)| {
+            let key = match value.is_boolean() {
+                true => FieldKey::EntityFlag(name.clone()),
+                false => FieldKey::EntityField(name.clone()),
+            };
+            Field::editable(key, name, display_value(value))
+        }));
+        for (name, target) in &custom.links {
+            let dangling = entity
+                .dangling
+                .is_some_and(|dangling| dangling.0.contains(name));
+            let value = match (target, dangling) {
+                (None, _) => "none".to_string(),
+                (Some(id), false) => format!("#{id}"),
+                (Some(id), true) => format!("#{id} (missing)"),
+            };
+            fields.push(Field::editable(
+                FieldKey::EntityLink(name.clone()),
+                &format!("{name} ->"),
+                value,
+            ));
+        }
+        (custom.kind.clone(), fields)
+    } else if let Some(hazard) = entity.hazard {
+        let kind = entity
+            .hazard_kind
+            .map(|kind| kind.0.as_str())
+            .unwrap_or_default();
+        let hazard_type = hazards.get(kind);
+        let fields = vec![
+            Field::editable(FieldKey::HazardIndex, "index", hazard.0),
+            Field::editable(FieldKey::HazardKind, "kind", &hazard_type.name),
+            Field::read_only("damage", hazard_type.damage),
+            Field::read_only("knockback", hazard_type.knockback),
+            Field::read_only("instant kill", hazard_type.instant_kill),
+        ];
+        ("Hazard".to_string(), fields)
+    } else if let Some(region) = entity.region {
+        (
+            "Region".to_string(),
+            vec![Field::editable(
+                FieldKey::RegionKind,
+                "kind",
+                region.0.label(),
+            )],
+        )
+    } else if let Some(tile) = entity.tile {
+        (
+            "Tile".to_string(),
+            vec![Field::editable(FieldKey::TileIndex, "index", tile.0)],
+        )
+    } else if entity.is_player {
+        ("Player".to_string(), Vec::new())
+    } else {
+        ("Entity".to_string(), Vec::new())
+    };
+    if let Some(collider) = entity.collider {
+        fields.push(Field::editable(
+            FieldKey::ColliderPos,
+            "collider pos",
+            format!("{}, {}", collider.pos.x, collider.pos.y),
+        ));
+        // only regions save their size, everything else is one cell
+        let size = format!("{}, {}", collider.size.x, collider.size.y);
+        fields.push(match entity.region {
+            Some(_) => Field::editable(FieldKey::ColliderSize, "collider size", size),
+            None => Field::read_only("collider size", size),
+        });
+    }
+    (title, fields)
+}
+
+fn parse_pair(value: &str) -> Result<Vec2, String> {
+    let parts: Vec<&str> = value
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|part| !part.is_empty())
+        .collect();
+    match parts[..] {
+        [x, y] => match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => Ok(Vec2::new(x, y)),
+            _ => Err(format!("{value} is not a pair of numbers")),
+        },
+        _ => Err("expected x, y".to_string()),
+    }
+}
+
+// sets the atlas index of a tile or hazard sprite, checked against the atlas layout
+fn apply_index(
+    atlas: Option<&mut TextureAtlas>,
+    layouts: &Assets<TextureAtlasLayout>,
+    value: &str,
+) -> Result<usize, String> {
+    let index: usize = value
+        .parse()
+        .map_err(|_| format!("{value} is not a tile index"))?;
+    let atlas = atlas.ok_or("entity has no atlas sprite")?;
+    if let Some(layout) = layouts.get(&atlas.layout) {
+        if index >= layout.len() {
+            return Err(format!("the tileset has only {} tiles", layout.len()));
+        }
+    }
+    atlas.index = index;
+    Ok(index)
+}
+
+// the layer of a placed entity, a cell holds at most one of each like painting leaves it
+fn layer(tile: bool, hazard: bool, mob: bool, custom: bool) -> Option<&'static str> {
+    match (tile, hazard, mob, custom) {
+        (_, true, ..) => Some("hazard"),
+        (true, ..) => Some("tile"),
+        (_, _, true, _) => Some("mob"),
+        (.., true) => Some("entity"),
+        _ => None,
+    }
+}
+
+// where a moved entity may go, the same cells painting it there would allow
+#[derive(Default)]
+struct Placement {
+    bounds: LevelBounds,
+    occupied: HashSet<(&'static str, IVec2)>,
+}
+
+impl Placement {
+    fn new(entity_q: &Query<Inspected>, bounds: LevelBounds) -> Self {
+        let occupied = entity_q
+            .iter()
+            .filter_map(|entity| {
+                let layer = layer(
+                    entity.tile.is_some(),
+                    entity.hazard.is_some(),
+                    entity.mob.is_some(),
+                    entity.custom.is_some(),
+                )?;
+                Some((layer, world_to_cell(entity.transform.translation)))
+            })
+            .collect();
+        Self { bounds, occupied }
+    }
+
+    fn check(&self, layer: Option<&'static str>, to: IVec2) -> Result<(), String> {
+        if !self.bounds.contains(to) {
+            return Err(format!(
+                "cell {}, {} is outside the level bounds",
+                to.x, to.y
+            ));
+        }
+        match layer {
+            Some(layer) if self.occupied.contains(&(layer, to)) => {
+                Err(format!("cell {}, {} already has a {layer}", to.x, to.y))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn apply_field(
+    entity: &mut InspectedItem,
+    key: &FieldKey,
+    value: &str,
+    hazards: &HazardRegistry,
+    entities: &EntityRegistry,
+    layouts: &Assets<TextureAtlasLayout>,
+    placement: &Placement,
+) -> Result<(), String> {
+    let value = value.trim();
+    match key {
+        FieldKey::TileIndex => {
+            let tile = entity.tile.as_deref_mut().ok_or("not a tile")?;
+            tile.0 = apply_index(entity.atlas.as_deref_mut(), layouts, value)?;
+        }
+        FieldKey::HazardIndex => {
+            let hazard = entity.hazard.as_deref_mut().ok_or("not a hazard")?;
+            hazard.0 = apply_index(entity.atlas.as_deref_mut(), layouts, value)?;
+        }
+        FieldKey::HazardKind => {
+            let kind = entity.hazard_kind.as_deref_mut().ok_or("not a hazard")?;
+            // cycles through the registered kinds
+            let next = hazards
+                .position(&kind.0)
+                .map_or(0, |index| (index + 1) % hazards.types.len());
+            kind.0 = hazards.types[next].name.clone();
+        }
+        FieldKey::ColliderPos => {
+            let layer = layer(
+                entity.tile.is_some(),
+                entity.hazard.is_some(),
+                entity.mob.is_some(),
+                entity.custom.is_some(),
+            );
+            let collider = entity
+                .collider
+                .as_deref_mut()
+                .ok_or("entity has no collider")?;
+            let pos = parse_pair(value)?;
+            // colliders stay on the grid like everything placed with the mouse, regions
+            // are free-sized and keep the exact position
+            let pos = match entity.region {
+                Some(_) => pos,
+                None => cell_to_world(world_to_cell(pos.extend(0.0))).xy(),
+            };
+            let from = world_to_cell(entity.transform.translation);
+            let to = world_to_cell(pos.extend(0.0));
+            if to != from {
+                placement.check(layer, to)?;
+            }
+            entity.transform.translation = pos.extend(entity.transform.translation.z);
+            collider.pos = entity.transform.translation;
+        }
+        FieldKey::ColliderSize => {
+            if entity.region.is_none() {
+                return Err("only regions can be resized".to_string());
+            }
+            let collider = entity
+                .collider
+                .as_deref_mut()
+                .ok_or("entity has no collider")?;
+            let size = parse_pair(value)?;
+            if size.x <= 0.0 || size.y <= 0.0 {
+                return Err("collider size must be positive".to_string());
+            }
+            collider.size = size;
+        }
+        FieldKey::RegionKind => {
+            let region = entity.region.as_deref_mut().ok_or("not a region")?;
+            region.0 = region.0.next();
+        }
+        FieldKey::EntityField(name) | FieldKey::EntityFlag(name) => {
+            let custom = entity.custom.as_deref_mut().ok_or("not a custom entity")?;
+            let current = custom.fields.get(name).ok_or("unknown field")?;
+            // the schema decides, a float field saved as 1 still takes 1.5
+            let field_type = entities
+                .field_type(&custom.kind, name)
+                .unwrap_or_else(|| FieldType::of(current));
+            let value = match current.as_bool() {
+                Some(flag) => (!flag).into(),
+                None => field_type.parse(value)?,
+            };
+            custom.fields.insert(name.clone(), value);
+        }
+        _ => {
+            let mob = entity.mob.as_deref_mut().ok_or("not a mob")?;
+            apply_mob_field(mob, entity.patrol.as_deref_mut(), key, value)?;
+        }
+    }
+    Ok(())
+}
+
+fn mob_fields(mob: &Mob, patrol: Option<&Patrol>) -> Vec<Field> {
+    let facing = match mob.facing {
+        Facing::Left => "left",
+        Facing::Right => "right",
+    };
+    let mut fields = vec![
+        Field::read_only("type", &mob.kind),
+        Field::editable(FieldKey::MobFacing, "facing", facing),
+        Field::editable(FieldKey::MobSpeed, "speed", mob.speed),
+        Field::editable(FieldKey::MobHealth, "health", mob.health),
+    ];
+    for (key, value) in &mob.properties {
+        fields.push(Field::editable(
+            FieldKey::MobProperty(key.clone()),
+            key,
+            value,
+        ));
+    }
+    fields.push(Field::editable(FieldKey::NewMobProperty, "+ key=value", ""));
+    if let Some(patrol) = patrol {
+        fields.push(Field::editable(
+            FieldKey::PatrolMode,
+            "patrol",
+            patrol.mode.label(),
+        ));
+        fields.push(Field::editable(
+            FieldKey::ClearWaypoints,
+            "waypoints (click to clear)",
+            patrol.waypoints.len(),
+        ));
+    }
+    fields
+}
+
+fn apply_mob_field(
+    mob: &mut Mob,
+    patrol: Option<&mut Patrol>,
+    key: &FieldKey,
+    value: &str,
+) -> Result<(), String> {
+    match key {
+        FieldKey::PatrolMode | FieldKey::ClearWaypoints => {
+            let patrol = patrol.ok_or("mob has no patrol")?;
+            match key {
+                FieldKey::PatrolMode => {
+                    patrol.mode = match patrol.mode {
+                        PatrolMode::Loop => PatrolMode::PingPong,
+                        PatrolMode::PingPong => PatrolMode::Loop,
+                    }
+                }
+                _ => patrol.waypoints.clear(),
+            }
+        }
+        FieldKey::MobFacing => {
+            mob.facing = match mob.facing {
+                Facing::Left => Facing::Right,
+                Facing::Right => Facing::Left,
+            };
+        }
+        FieldKey::MobSpeed => {
+            mob.speed = value
+                .parse()
+                .map_err(|_| format!("{value} is not a number"))?;
+        }
+        FieldKey::MobHealth => {
+            mob.health = value
+                .parse()
+                .map_err(|_| format!("{value} is not a whole number"))?;
+        }
+        // clearing a custom property removes it
+        FieldKey::MobProperty(name) => match value.is_empty() {
+            true => {
+                mob.properties.remove(name);
+            }
+            false => {
+                mob.properties.insert(name.clone(), value.to_string());
+            }
+        },
+        FieldKey::NewMobProperty => {
+            let Some((name, value)) = value.split_once('=') else {
+                return Err("expected key=value".to_string());
+            };
+            if name.trim().is_empty() {
+                return Err("property name is empty".to_string());
+            }
+            mob.properties
+                .insert(name.trim().to_string(), value.trim().to_string());
+        }
+        FieldKey::TileIndex
+        | FieldKey::HazardIndex
+        | FieldKey::HazardKind
+        | FieldKey::ColliderPos
+        | FieldKey::ColliderSize
+        | FieldKey::EntityField(_)
+        | FieldKey::EntityFlag(_)
+        | FieldKey::EntityLink(_)
+        | FieldKey::RegionKind
+        | FieldKey::TileCollision
+        | FieldKey::TileFriction
+        | FieldKey::TileTags
+        | FieldKey::BoundsX
+        | FieldKey::BoundsY
+        | FieldKey::BoundsWidth
+        | FieldKey::BoundsHeight
+        | FieldKey::Background
+        | FieldKey::ResizeAnchor
+        | FieldKey::ResizeCrop
+        | FieldKey::LevelName
+        | FieldKey::LevelAuthor
+        | FieldKey::LevelMusic
+        | FieldKey::LevelParTime
+        | FieldKey::LevelProperty(_)
+        | FieldKey::NewLevelProperty => return Err(format!("{key:?} is not a mob field")),
+    }
+    Ok(())
+}
+
+pub fn setup_inspector(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(0.0),
+                top: Val::Px(100.0),
+                width: Val::Px(260.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(InspectorPanel);
+}
+
+#[allow(clippy::type_complexity)]
+pub fn select_on_click(
+    mut click_event_r: EventReader<ClickEvent>,
+    state: Res<State<ClickAnd>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    node_q: Query<(&GlobalTransform, &Node)>,
+    level_q: Query<
+        (
+            Entity,
+            &Transform,
+            Has<Mob>,
+            Has<CustomEntity>,
+            Has<Hazard>,
+            Has<Tile>,
+        ),
+        With<LevelEntity>,
+    >,
+    mut selection: ResMut<Selection>,
+    mut edit: ResMut<FieldEdit>,
+) {
+    let Some(click_event) = click_event_r.read().last() else {
+        return;
+    };
+    if *state.get() != ClickAnd::Select || cursor_over_ui(click_event.cursor_pos, &node_q) {
+        return;
+    }
+
+    let (camera, camera_transform) = cam_q.single();
+    let click_pos = screen_to_world(camera, camera_transform, click_event.cursor_pos);
+
+    // mobs sit on top of custom entities, then hazards, then tiles
+    let picked = level_q
+        .iter()
+        .filter(|(_, transform, ..)| transform.translation.xy() == click_pos.xy())
+        .max_by_key(|(_, _, is_mob, is_entity, is_hazard, is_tile)| {
+            (*is_mob, *is_entity, *is_hazard, *is_tile)
+        })
+        .map(|(ent, ..)| ent);
+
+    if selection.0 != picked {
+        selection.0 = picked;
+        edit.field = None;
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn refresh_inspector(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut selection: ResMut<Selection>,
+    edit: Res<FieldEdit>,
+    hazards: Res<HazardRegistry>,
+    entity_q: Query<InspectedReadOnly>,
+    changed_q: Query<
+        (),
+        Or<(
+            Changed<Mob>,
+            Changed<Patrol>,
+            Changed<Tile>,
+            Changed<Hazard>,
+            Changed<HazardKind>,
+            Changed<Collider>,
+            Changed<CustomEntity>,
+            Changed<EntityId>,
+            Changed<DanglingLinks>,
+            Changed<Region>,
+        )>,
+    >,
+    mut panel_q: Query<(Entity, &mut Style), With<InspectorPanel>>,
+) {
+    let erased = selection.0.is_some_and(|ent| entity_q.get(ent).is_err());
+    let changed = selection.0.is_some_and(|ent| changed_q.contains(ent));
+    if !(selection.is_changed() || edit.is_changed() || changed || erased) {
+        return;
+    }
+    let Ok((panel, mut style)) = panel_q.get_single_mut() else {
+        return;
+    };
+    commands.entity(panel).despawn_descendants();
+
+    let Some(Ok(entity)) = selection.0.map(|ent| entity_q.get(ent)) else {
+        // the selected entity was erased
+        if selection.0.is_some() {
+            selection.0 = None;
+        }
+        style.display = Display::None;
+        return;
+    };
+    style.display = Display::Flex;
+
+    let (title, fields) = entity_fields(&entity, &hazards);
+
+    spawn_fields(&mut commands, panel, &title, fields, &edit, &asset_server);
+}
+
+// rows of a field panel, editable fields become InspectorField buttons
+pub fn spawn_fields(
+    commands: &mut Commands,
+    panel: Entity,
+    title: &str,
+    fields: Vec<Field>,
+    edit: &FieldEdit,
+    asset_server: &AssetServer,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+        font_size: 18.0,
+        color: HOVER_BORDER,
+    };
+    let button = ButtonBundle {
+        style: Style {
+            padding: UiRect::horizontal(Val::Px(4.0)),
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        border_color: BorderColor(BORDER_COLOR),
+        ..default()
+    };
+
+    commands.entity(panel).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            title,
+            TextStyle {
+                font_size: 22.0,
+                ..text_style.clone()
+            },
+        ));
+        for field in fields {
+            let Some(key) = field.key else {
+                parent.spawn(TextBundle::from_section(
+                    format!("{}: {}", field.label, field.value),
+                    text_style.clone(),
+                ));
+                continue;
+            };
+            let editing = edit.field.as_ref() == Some(&key);
+            let (value, border) = match editing {
+                true => (format!("{}_", edit.buffer), BorderColor(PRESSED_BORDER)),
+                false => (field.value, BorderColor(BORDER_COLOR)),
+            };
+            parent
+                .spawn(ButtonBundle {
+                    border_color: border,
+                    ..button.clone()
+                })
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section(
+                        format!("{}: {value}", field.label),
+                        text_style.clone(),
+                    ));
+                })
+                .insert(InspectorField(key));
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn inspector_field_interaction(
+    interaction_q: Query<(&Interaction, &InspectorField), Changed<Interaction>>,
+    selection: Res<Selection>,
+    mut edit: ResMut<FieldEdit>,
+    hazards: Res<HazardRegistry>,
+    entities: Res<EntityRegistry>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut entity_q: Query<Inspected>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut pending_link: ResMut<PendingLink>,
+    mut tool_state: ResMut<NextState<ClickAnd>>,
+    selected_tile: Res<SelectedTile>,
+    mut tile_properties: ResMut<TileProperties>,
+    mut level_properties: ResMut<LevelProperties>,
+) {
+    for (interaction, InspectorField(key)) in &interaction_q {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if key.is_level_property() {
+            if key.applies_on_click() {
+                if let Err(err) = level_properties.apply(key, "") {
+                    warn!("{err}");
+                }
+                continue;
+            }
+            edit.buffer = level_property_fields(&level_properties)
+                .into_iter()
+                .find(|field| field.key.as_ref() == Some(key))
+                .map(|field| field.value)
+                .unwrap_or_default();
+            edit.field = Some(key.clone());
+            continue;
+        }
+        if key.is_tile_property() {
+            if key.applies_on_click() {
+                if let Err(err) = tile_properties.apply(selected_tile.0, key, "") {
+                    warn!("{err}");
+                }
+                continue;
+            }
+            edit.buffer = tile_property_fields(&tile_properties.get(selected_tile.0))
+                .into_iter()
+                .find(|field| field.key.as_ref() == Some(key))
+                .map(|field| field.value)
+                .unwrap_or_default();
+            edit.field = Some(key.clone());
+            continue;
+        }
+        let Some(ent) = selection.0 else {
+            continue;
+        };
+        // the link target is picked on the canvas
+        if let FieldKey::EntityLink(name) = key {
+            pending_link.0 = Some(name.clone());
+            tool_state.set(ClickAnd::Link);
+            continue;
+        }
+        if key.applies_on_click() {
+            let Ok(mut entity) = entity_q.get_mut(ent) else {
+                continue;
+            };
+            // fields applied on click never move the entity, so any placement will do
+            match apply_field(
+                &mut entity,
+                key,
+                "",
+                &hazards,
+                &entities,
+                &layouts,
+                &Placement::default(),
+            ) {
+                Ok(()) => current_level.dirty = true,
+                Err(err) => warn!("{err}"),
+            }
+            continue;
+        }
+        let Ok(entity) = entity_q.get(ent) else {
+            continue;
+        };
+        edit.buffer = entity_fields(&entity, &hazards)
+            .1
+            .into_iter()
+            .find(|field| field.key.as_ref() == Some(key))
+            .map(|field| field.value)
+            .unwrap_or_default();
+        edit.field = Some(key.clone());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn inspector_text_input(
+    mut keyboard_r: EventReader<KeyboardInput>,
+    mut selection: ResMut<Selection>,
+    mut edit: ResMut<FieldEdit>,
+    hazards: Res<HazardRegistry>,
+    entities: Res<EntityRegistry>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    mut entity_q: Query<Inspected>,
+    mut current_level: ResMut<CurrentLevel>,
+    selected_tile: Res<SelectedTile>,
+    mut tile_properties: ResMut<TileProperties>,
+    mut level_properties: ResMut<LevelProperties>,
+    mut terrain_changed_w: EventWriter<TerrainChanged>,
+) {
+    for event in keyboard_r.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        let Some(key) = edit.field.clone() else {
+            if event.logical_key == Key::Escape {
+                selection.0 = None;
+            }
+            continue;
+        };
+        match &event.logical_key {
+            Key::Character(chars) => edit.buffer.push_str(chars),
+            Key::Space => edit.buffer.push(' '),
+            Key::Backspace => {
+                edit.buffer.pop();
+            }
+            Key::Escape => edit.field = None,
+            Key::Enter => {
+                let buffer = std::mem::take(&mut edit.buffer);
+                edit.field = None;
+                if key.is_tile_property() {
+                    if let Err(err) = tile_properties.apply(selected_tile.0, &key, &buffer) {
+                        warn!("{err}");
+                    }
+                    continue;
+                }
+                if key.is_level_property() {
+                    match level_properties.apply(&key, &buffer) {
+                        Ok(()) => current_level.dirty = true,
+                        Err(err) => warn!("{err}"),
+                    }
+                    continue;
+                }
+                let placement = Placement::new(&entity_q, level_properties.bounds);
+                let Some(Ok(mut entity)) = selection.0.map(|ent| entity_q.get_mut(ent)) else {
+                    continue;
+                };
+                let from = world_to_cell(entity.transform.translation);
+                match apply_field(
+                    &mut entity,
+                    &key,
+                    &buffer,
+                    &hazards,
+                    &entities,
+                    &layouts,
+                    &placement,
+                ) {
+                    Ok(()) => current_level.dirty = true,
+                    Err(err) => warn!("{err}"),
+                }
+                // a moved terrain tile changes the neighbours at both ends
+                let to = world_to_cell(entity.transform.translation);
+                if entity.terrain.is_some() && to != from {
+                    terrain_changed_w.send(TerrainChanged(from));
+                    terrain_changed_w.send(TerrainChanged(to));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+pub fn draw_selection(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    transform_q: Query<&Transform>,
+) {
+    let Some(Ok(transform)) = selection.0.map(|ent| transform_q.get(ent)) else {
+        return;
+    };
+    gizmos.rect_2d(
+        transform.translation.xy(),
+        0.0,
+        Vec2::splat(28.0),
+        PRESSED_BORDER,
+    );
+}
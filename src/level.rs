@@ -0,0 +1,680 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    autotile::Terrain,
+    cell_to_world,
+    collision::{merge_rects, outlines, CollisionExport},
+    dialog::{DialogAction, PendingAction},
+    entities::{spawn_custom_entity, CustomEntity, EntityRegistry},
+    hazards::HazardKind,
+    level_properties::{LevelBounds, LevelMeta, LevelProperties},
+    links::{EntityId, NextEntityId},
+    mobs::{spawn_mob, MobRegistry},
+    patrol::{Patrol, PatrolMode},
+    player_bundle,
+    regions::{region_bundle, Region, RegionKind},
+    tile_bundle,
+    tile_properties::{CollisionType, TileProperties, TileProps},
+    tileset::Tileset,
+    validation::ValidateLevel,
+    world_to_cell, Collider, ColliderBundle, Facing, Hazard, LevelEntity, Mob, Player, TextInput,
+    Tile, TileOrientation,
+};
+
+#[derive(Resource)]
+pub struct CurrentLevel {
+    pub path: PathBuf,
+    // unsaved changes since the last save or load
+    pub dirty: bool,
+}
+
+impl Default for CurrentLevel {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("level.json"),
+            dirty: false,
+        }
+    }
+}
+
+pub fn recovery_path(path: &Path) -> PathBuf {
+    path.with_extension("recovery.json")
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct CellData {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl CellData {
+    pub fn from_world(pos: Vec3) -> Self {
+        let cell = world_to_cell(pos);
+        Self {
+            x: cell.x,
+            y: cell.y,
+        }
+    }
+
+    pub fn to_world(self) -> Vec3 {
+        cell_to_world(IVec2::new(self.x, self.y))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TileData {
+    #[serde(flatten)]
+    pub cell: CellData,
+    pub index: usize,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_x: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub flip_y: bool,
+    // clockwise quarter turns
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub rotation: u8,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terrain: Option<usize>,
+    // hazard kind name, unset for tiles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+}
+
+impl TileData {
+    fn new(pos: Vec3, index: usize, orientation: Option<&TileOrientation>) -> Self {
+        let orientation = orientation.copied().unwrap_or_default();
+        Self {
+            cell: CellData::from_world(pos),
+            index,
+            flip_x: orientation.flip_x,
+            flip_y: orientation.flip_y,
+            rotation: orientation.rotation,
+            terrain: None,
+            kind: None,
+        }
+    }
+
+    fn orientation(&self) -> TileOrientation {
+        TileOrientation {
+            flip_x: self.flip_x,
+            flip_y: self.flip_y,
+            rotation: self.rotation % 4,
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn is_zero(value: &u8) -> bool {
+    *value == 0
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MobData {
+    #[serde(flatten)]
+    pub cell: CellData,
+    #[serde(default)]
+    pub kind: String,
+    #[serde(default)]
+    pub facing: Facing,
+    #[serde(default)]
+    pub speed: f32,
+    #[serde(default)]
+    pub health: i32,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub waypoints: Vec<CellData>,
+    #[serde(default)]
+    pub patrol_mode: PatrolMode,
+}
+
+impl MobData {
+    fn new(pos: Vec3, mob: &Mob, patrol: Option<&Patrol>) -> Self {
+        let patrol = patrol.cloned().unwrap_or_default();
+        Self {
+            cell: CellData::from_world(pos),
+            kind: mob.kind.clone(),
+            facing: mob.facing,
+            speed: mob.speed,
+            health: mob.health,
+            properties: mob.properties.clone(),
+            waypoints: patrol
+                .waypoints
+                .iter()
+                .map(|cell| CellData {
+                    x: cell.x,
+                    y: cell.y,
+                })
+                .collect(),
+            patrol_mode: patrol.mode,
+        }
+    }
+
+    fn patrol(&self) -> Patrol {
+        Patrol {
+            waypoints: self
+                .waypoints
+                .iter()
+                .map(|cell| IVec2::new(cell.x, cell.y))
+                .collect(),
+            mode: self.patrol_mode,
+        }
+    }
+
+    fn mob(&self) -> Mob {
+        Mob {
+            kind: self.kind.clone(),
+            facing: self.facing,
+            speed: self.speed,
+            health: self.health,
+            properties: self.properties.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct EntityData {
+    #[serde(flatten)]
+    pub cell: CellData,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, serde_json::Value>,
+    // only links that are set, by field name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub links: BTreeMap<String, u64>,
+}
+
+// a free-sized region, x and y are its bottom-left corner in world units
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RegionData {
+    pub kind: RegionKind,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl RegionData {
+    fn new(region: &Region, collider: &Collider) -> Self {
+        let min = collider.pos.xy() - collider.size / 2.0;
+        Self {
+            kind: region.0,
+            x: min.x,
+            y: min.y,
+            width: collider.size.x,
+            height: collider.size.y,
+        }
+    }
+}
+
+// a merged block of solid cells, in cell units
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CellRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+// a single sloped cell, slopes aren't merged since each one is its own ramp
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SlopeData {
+    #[serde(flatten)]
+    pub cell: CellData,
+    pub collision: CollisionType,
+}
+
+/// Merged collision shapes for the solid tiles, written on export for the game to use
+/// instead of one collider per tile. Outlines are closed loops of cell corners. Never
+/// read back, the editor rebuilds it from the tiles and the tile property table.
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct CollisionData {
+    pub rects: Vec<CellRect>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub one_way: Vec<CellRect>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub slopes: Vec<SlopeData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outlines: Vec<Vec<CellData>>,
+}
+
+fn cell_rects(cells: &HashSet<IVec2>) -> Vec<CellRect> {
+    merge_rects(cells)
+        .into_iter()
+        .map(|rect| CellRect {
+            x: rect.min.x,
+            y: rect.min.y,
+            width: rect.width(),
+            height: rect.height(),
+        })
+        .collect()
+}
+
+impl CollisionData {
+    fn new(cells: &[(IVec2, CollisionType)], with_outlines: bool) -> Self {
+        let of_type = |collision: CollisionType| -> HashSet<IVec2> {
+            cells
+                .iter()
+                .filter(|(_, kind)| *kind == collision)
+                .map(|(cell, _)| *cell)
+                .collect()
+        };
+        let solid = of_type(CollisionType::Solid);
+        let rects = cell_rects(&solid);
+        let one_way = cell_rects(&of_type(CollisionType::OneWay));
+        let slopes = cells
+            .iter()
+            .filter(|(_, kind)| matches!(kind, CollisionType::SlopeUp | CollisionType::SlopeDown))
+            .map(|(cell, kind)| SlopeData {
+                cell: CellData {
+                    x: cell.x,
+                    y: cell.y,
+                },
+                collision: *kind,
+            })
+            .collect();
+        let outlines = match with_outlines {
+            true => outlines(&solid)
+                .into_iter()
+                .map(|points| {
+                    points
+                        .into_iter()
+                        .map(|point| CellData {
+                            x: point.x,
+                            y: point.y,
+                        })
+                        .collect()
+                })
+                .collect(),
+            false => Vec::new(),
+        };
+        Self {
+            rects,
+            one_way,
+            slopes,
+            outlines,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+            && self.one_way.is_empty()
+            && self.slopes.is_empty()
+            && self.outlines.is_empty()
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct LevelData {
+    pub tileset: String,
+    // missing in levels saved before bounds existed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bounds: Option<LevelBounds>,
+    #[serde(flatten)]
+    pub meta: LevelMeta,
+    pub player: Option<CellData>,
+    pub tiles: Vec<TileData>,
+    pub hazards: Vec<TileData>,
+    pub mobs: Vec<MobData>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub entities: Vec<EntityData>,
+    // the id the next placed entity gets, so ids of erased entities are never reused
+    #[serde(default, skip_serializing_if = "is_first_id")]
+    pub next_id: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<RegionData>,
+    #[serde(default, skip_serializing_if = "CollisionData::is_empty")]
+    pub collision: CollisionData,
+    // the tileset's property entries for the tiles this level uses, export only
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tile_properties: BTreeMap<usize, TileProps>,
+}
+
+fn is_first_id(id: &u64) -> bool {
+    *id <= 1
+}
+
+impl LevelData {
+    // older levels have no counter, the next id then follows the highest saved one
+    pub fn next_entity_id(&self) -> u64 {
+        let after_saved = self
+            .entities
+            .iter()
+            .filter_map(|entity| entity.id)
+            .max()
+            .map_or(1, |id| id + 1);
+        self.next_id.max(after_saved)
+    }
+
+    // the saved bounds, or bounds fitted around the content of an older level
+    pub fn bounds(&self) -> LevelBounds {
+        self.bounds.unwrap_or_else(|| {
+            let cells = self
+                .player
+                .iter()
+                .chain(self.tiles.iter().map(|tile| &tile.cell))
+                .chain(self.hazards.iter().map(|hazard| &hazard.cell))
+                .chain(self.mobs.iter().map(|mob| &mob.cell))
+                .chain(self.entities.iter().map(|entity| &entity.cell))
+                .map(|cell| IVec2::new(cell.x, cell.y));
+            LevelBounds::fitting(cells)
+        })
+    }
+}
+
+#[derive(SystemParam)]
+#[allow(clippy::type_complexity)]
+pub struct LevelQuery<'w, 's> {
+    player_q: Query<'w, 's, &'static Transform, (With<Player>, With<LevelEntity>)>,
+    tile_q: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            &'static Tile,
+            Option<&'static TileOrientation>,
+            Option<&'static Terrain>,
+        ),
+        With<LevelEntity>,
+    >,
+    hazard_q: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            &'static Hazard,
+            Option<&'static TileOrientation>,
+            Option<&'static HazardKind>,
+        ),
+        With<LevelEntity>,
+    >,
+    mob_q: Query<
+        'w,
+        's,
+        (&'static Transform, &'static Mob, Option<&'static Patrol>),
+        With<LevelEntity>,
+    >,
+    entity_q: Query<
+        'w,
+        's,
+        (
+            &'static Transform,
+            &'static CustomEntity,
+            Option<&'static EntityId>,
+        ),
+        With<LevelEntity>,
+    >,
+    region_q: Query<'w, 's, (&'static Region, &'static Collider), With<LevelEntity>>,
+    collision_export: Res<'w, CollisionExport>,
+    tile_properties: Res<'w, TileProperties>,
+    level_properties: Res<'w, LevelProperties>,
+    next_entity_id: Res<'w, NextEntityId>,
+}
+
+impl LevelQuery<'_, '_> {
+    pub fn level_data(&self, tileset: &str) -> LevelData {
+        let cells: Vec<(IVec2, CollisionType)> = self
+            .tile_q
+            .iter()
+            .map(|(transform, tile, ..)| {
+                (
+                    world_to_cell(transform.translation),
+                    self.tile_properties.collision(tile.0),
+                )
+            })
+            .collect();
+        let tile_properties = self
+            .tile_q
+            .iter()
+            .filter_map(|(_, tile, ..)| {
+                let props = self.tile_properties.tiles.get(&tile.0)?;
+                Some((tile.0, props.clone()))
+            })
+            .collect();
+        LevelData {
+            tileset: tileset.to_string(),
+            bounds: Some(self.level_properties.bounds),
+            meta: self.level_properties.meta.clone(),
+            player: self
+                .player_q
+                .iter()
+                .next()
+                .map(|transform| CellData::from_world(transform.translation)),
+            tiles: self
+                .tile_q
+                .iter()
+                .map(|(transform, tile, orientation, terrain)| TileData {
+                    terrain: terrain.map(|terrain| terrain.0),
+                    ..TileData::new(transform.translation, tile.0, orientation)
+                })
+                .collect(),
+            hazards: self
+                .hazard_q
+                .iter()
+                .map(|(transform, hazard, orientation, kind)| TileData {
+                    kind: kind.map(|kind| kind.0.clone()),
+                    ..TileData::new(transform.translation, hazard.0, orientation)
+                })
+                .collect(),
+            mobs: self
+                .mob_q
+                .iter()
+                .map(|(transform, mob, patrol)| MobData::new(transform.translation, mob, patrol))
+                .collect(),
+            entities: self
+                .entity_q
+                .iter()
+                .map(|(transform, entity, id)| EntityData {
+                    cell: CellData::from_world(transform.translation),
+                    id: id.map(|id| id.0),
+                    kind: entity.kind.clone(),
+                    fields: entity.fields.clone(),
+                    links: entity
+                        .links
+                        .iter()
+                        .filter_map(|(name, target)| Some((name.clone(), (*target)?)))
+                        .collect(),
+                })
+                .collect(),
+            next_id: self.next_entity_id.0,
+            regions: self
+                .region_q
+                .iter()
+                .map(|(region, collider)| RegionData::new(region, collider))
+                .collect(),
+            collision: CollisionData::new(&cells, self.collision_export.outlines),
+            tile_properties,
+        }
+    }
+}
+
+pub fn read_level(path: &Path) -> Result<LevelData, String> {
+    let json = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    serde_json::from_str(&json).map_err(|err| err.to_string())
+}
+
+pub fn write_level(path: &Path, level: &LevelData) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(level).map_err(|err| err.to_string())?;
+    std::fs::write(path, json).map_err(|err| err.to_string())
+}
+
+pub fn spawn_level(
+    commands: &mut Commands,
+    level: &LevelData,
+    mob_registry: &MobRegistry,
+    entity_registry: &EntityRegistry,
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+) {
+    let size = Vec2::splat(24.0);
+
+    for tile in &level.tiles {
+        let pos = tile.cell.to_world();
+        let mut ent = commands.spawn(tile_bundle(
+            pos,
+            tile.index,
+            tile.orientation(),
+            texture.clone(),
+            layout.clone(),
+        ));
+        ent.insert(ColliderBundle::new(pos, size, Tile(tile.index)));
+        if let Some(terrain) = tile.terrain {
+            ent.insert(Terrain(terrain));
+        }
+    }
+    for hazard in &level.hazards {
+        let pos = hazard.cell.to_world();
+        commands
+            .spawn(tile_bundle(
+                pos,
+                hazard.index,
+                hazard.orientation(),
+                texture.clone(),
+                layout.clone(),
+            ))
+            .insert(ColliderBundle::new(pos, size, Hazard(hazard.index)))
+            .insert(HazardKind(hazard.kind.clone().unwrap_or_default()));
+    }
+    for mob in &level.mobs {
+        let ent = spawn_mob(
+            commands,
+            mob.cell.to_world(),
+            mob.mob(),
+            mob_registry,
+            &texture,
+            &layout,
+        );
+        commands.entity(ent).insert(mob.patrol());
+    }
+    for entity in &level.entities {
+        let ent = spawn_custom_entity(
+            commands,
+            entity.cell.to_world(),
+            entity_registry.restore(&entity.kind, &entity.fields, &entity.links),
+            entity_registry,
+            &texture,
+            &layout,
+        );
+        if let Some(id) = entity.id {
+            commands.entity(ent).insert(EntityId(id));
+        }
+    }
+    for region in &level.regions {
+        let min = Vec2::new(region.x, region.y);
+        let size = Vec2::new(region.width, region.height).max(Vec2::ONE);
+        commands.spawn(region_bundle(min, min + size, region.kind));
+    }
+    if let Some(player) = level.player {
+        let pos = player.to_world();
+        commands
+            .spawn(player_bundle(pos))
+            .insert(ColliderBundle::new(pos, size, Player));
+    }
+}
+
+#[derive(Event, Clone, Copy)]
+pub struct SaveLevel;
+
+// opens a level file in place of the current one, CTRL-o reopens the current path
+#[derive(Event, Clone)]
+pub struct OpenLevel(pub PathBuf);
+
+pub fn save_level(
+    key_pressed: Res<ButtonInput<KeyCode>>,
+    mut save_r: EventReader<SaveLevel>,
+    text_res: Res<TextInput>,
+    mut current_level: ResMut<CurrentLevel>,
+    level_q: LevelQuery,
+    mut validate_w: EventWriter<ValidateLevel>,
+) {
+    let requested = save_r.read().count() > 0
+        || (key_pressed.pressed(KeyCode::ControlLeft) && key_pressed.just_pressed(KeyCode::KeyS));
+    if !requested {
+        return;
+    }
+
+    let level = level_q.level_data(&text_res.0);
+    let path = &current_level.path;
+    match write_level(path, &level) {
+        Ok(()) => {
+            info!("saved level to {}", path.display());
+            // an explicit save supersedes any autosave
+            let _ = std::fs::remove_file(recovery_path(path));
+            current_level.dirty = false;
+            validate_w.send(ValidateLevel { show_clean: false });
+        }
+        Err(err) => error!("failed to write {}: {err}", path.display()),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn load_level(
+    mut commands: Commands,
+    key_pressed: Res<ButtonInput<KeyCode>>,
+    mut open_r: EventReader<OpenLevel>,
+    asset_server: Res<AssetServer>,
+    mut tileset: ResMut<Tileset>,
+    mut text_res: ResMut<TextInput>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut level_properties: ResMut<LevelProperties>,
+    mut next_entity_id: ResMut<NextEntityId>,
+    mob_registry: Res<MobRegistry>,
+    entity_registry: Res<EntityRegistry>,
+    level_q: Query<Entity, With<LevelEntity>>,
+    mut pending: ResMut<PendingAction>,
+) {
+    let reopen =
+        key_pressed.pressed(KeyCode::ControlLeft) && key_pressed.just_pressed(KeyCode::KeyO);
+    // reopening drops unsaved edits, so it asks first like opening another level does
+    if reopen && current_level.dirty {
+        pending.0 = Some(DialogAction::Open(current_level.path.clone()));
+        return;
+    }
+    let requested = open_r.read().last().map(|open| open.0.clone());
+    let Some(path) = requested.or_else(|| reopen.then(|| current_level.path.clone())) else {
+        return;
+    };
+
+    let level = match read_level(&path) {
+        Ok(level) => level,
+        Err(err) => {
+            error!("failed to load {}: {err}", path.display());
+            return;
+        }
+    };
+
+    for ent in &level_q {
+        commands.entity(ent).despawn_recursive();
+    }
+
+    if !level.tileset.is_empty() && level.tileset != text_res.0 {
+        text_res.0 = level.tileset.clone();
+        tileset.texture = asset_server.load(text_res.0.clone());
+    }
+    level_properties.bounds = level.bounds();
+    level_properties.meta = level.meta.clone();
+    next_entity_id.0 = level.next_entity_id();
+    spawn_level(
+        &mut commands,
+        &level,
+        &mob_registry,
+        &entity_registry,
+        tileset.texture.clone(),
+        tileset.layout.clone(),
+    );
+
+    current_level.dirty = false;
+    info!("loaded level from {}", path.display());
+    current_level.path = path;
+}
@@ -0,0 +1,416 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    autotile::{Terrain, TerrainChanged},
+    entities::{display_value, FieldType},
+    inspector::{spawn_fields, Field, FieldEdit, FieldKey},
+    world_to_cell, LevelEntity, PANEL_COLOR, PRESSED_BORDER,
+};
+
+const BOUNDS_BACKGROUND_Z: f32 = 0.0;
+
+/// The playable area of a level in cells, `x` and `y` being its bottom-left cell. Tools
+/// don't paint outside of it and the background color fills it in the viewport.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+pub struct LevelBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    #[serde(default = "default_background")]
+    pub background: [f32; 3],
+}
+
+fn default_background() -> [f32; 3] {
+    [0.12, 0.12, 0.16]
+}
+
+impl Default for LevelBounds {
+    fn default() -> Self {
+        Self {
+            x: -32,
+            y: -18,
+            width: 64,
+            height: 36,
+            background: default_background(),
+        }
+    }
+}
+
+impl LevelBounds {
+    pub fn contains(&self, cell: IVec2) -> bool {
+        let rect = self.rect();
+        cell.cmpge(rect.min).all() && cell.cmplt(rect.max).all()
+    }
+
+    pub fn contains_world(&self, pos: Vec2) -> bool {
+        self.contains(world_to_cell(pos.extend(0.0)))
+    }
+
+    // in cells, max exclusive
+    pub fn rect(&self) -> IRect {
+        IRect::new(self.x, self.y, self.x + self.width, self.y + self.height)
+    }
+
+    pub fn world_rect(&self) -> Rect {
+        let rect = self.rect();
+        Rect::from_corners(rect.min.as_vec2() * 24.0, rect.max.as_vec2() * 24.0)
+    }
+
+    // the default bounds grown to fit cells placed before levels had bounds
+    pub fn fitting(cells: impl Iterator<Item = IVec2>) -> Self {
+        let rect = cells.fold(Self::default().rect(), |rect, cell| {
+            rect.union_point(cell).union_point(cell + IVec2::ONE)
+        });
+        Self {
+            x: rect.min.x,
+            y: rect.min.y,
+            width: rect.width(),
+            height: rect.height(),
+            background: default_background(),
+        }
+    }
+
+    pub fn background_color(&self) -> Color {
+        let [r, g, b] = self.background;
+        Color::srgb(r, g, b)
+    }
+}
+
+/// Descriptive properties of a level, written at the root of the level document next to
+/// `tileset`. `properties` holds whatever else a game wants to know about the level,
+/// typed like entity fields.
+#[derive(Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+pub struct LevelMeta {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub author: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub music: String,
+    // seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub par_time: Option<f32>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, Value>,
+}
+
+// the type a newly added property gets from how its value is written
+fn infer_value(text: &str) -> Value {
+    [FieldType::Bool, FieldType::Int, FieldType::Float]
+        .iter()
+        .find_map(|field_type| field_type.parse(text).ok())
+        .unwrap_or_else(|| Value::from(text))
+}
+
+// which side of the bounds stays put when the level is resized
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum Anchor {
+    #[default]
+    BottomLeft,
+    Bottom,
+    BottomRight,
+    Left,
+    Center,
+    Right,
+    TopLeft,
+    Top,
+    TopRight,
+}
+
+impl Anchor {
+    const ALL: [Anchor; 9] = [
+        Anchor::BottomLeft,
+        Anchor::Bottom,
+        Anchor::BottomRight,
+        Anchor::Left,
+        Anchor::Center,
+        Anchor::Right,
+        Anchor::TopLeft,
+        Anchor::Top,
+        Anchor::TopRight,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Anchor::BottomLeft => "bottom left",
+            Anchor::Bottom => "bottom",
+            Anchor::BottomRight => "bottom right",
+            Anchor::Left => "left",
+            Anchor::Center => "center",
+            Anchor::Right => "right",
+            Anchor::TopLeft => "top left",
+            Anchor::Top => "top",
+            Anchor::TopRight => "top right",
+        }
+    }
+
+    fn next(&self) -> Self {
+        let index = Self::ALL
+            .iter()
+            .position(|anchor| anchor == self)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    // how much of a size change moves the origin, 0 keeps the low side, 2 the high side
+    fn weight(&self) -> IVec2 {
+        let index = Self::ALL
+            .iter()
+            .position(|anchor| anchor == self)
+            .unwrap_or(0) as i32;
+        IVec2::new(index % 3, index / 3)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LevelProperties {
+    pub bounds: LevelBounds,
+    pub meta: LevelMeta,
+    pub anchor: Anchor,
+    // whether resizing erases what ends up outside the bounds
+    pub crop: bool,
+    // set by a resize with crop on, cleared once the level has been cropped
+    pub pending_crop: bool,
+}
+
+impl LevelProperties {
+    pub fn apply(&mut self, key: &FieldKey, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        let number = || {
+            value
+                .parse::<i32>()
+                .map_err(|_| format!("{value} is not a whole number"))
+        };
+        match key {
+            FieldKey::BoundsX => self.bounds.x = number()?,
+            FieldKey::BoundsY => self.bounds.y = number()?,
+            FieldKey::BoundsWidth => self.resize(IVec2::new(number()?, self.bounds.height))?,
+            FieldKey::BoundsHeight => self.resize(IVec2::new(self.bounds.width, number()?))?,
+            FieldKey::Background => {
+                let color = Srgba::hex(value).map_err(|_| format!("{value} is not a hex color"))?;
+                self.bounds.background = [color.red, color.green, color.blue];
+            }
+            FieldKey::ResizeAnchor => self.anchor = self.anchor.next(),
+            FieldKey::ResizeCrop => self.crop = !self.crop,
+            FieldKey::LevelName => self.meta.name = value.to_string(),
+            FieldKey::LevelAuthor => self.meta.author = value.to_string(),
+            FieldKey::LevelMusic => self.meta.music = value.to_string(),
+            // cleared means no par time
+            FieldKey::LevelParTime => {
+                self.meta.par_time = match value.is_empty() {
+                    true => None,
+                    false => Some(
+                        value
+                            .parse()
+                            .map_err(|_| format!("{value} is not a number"))?,
+                    ),
+                }
+            }
+            // clearing a property removes it, otherwise it keeps its type
+            FieldKey::LevelProperty(name) => match value.is_empty() {
+                true => {
+                    self.meta.properties.remove(name);
+                }
+                false => {
+                    let field_type = self
+                        .meta
+                        .properties
+                        .get(name)
+                        .map_or(FieldType::String, FieldType::of);
+                    let value = field_type.parse(value)?;
+                    self.meta.properties.insert(name.clone(), value);
+                }
+            },
+            FieldKey::NewLevelProperty => {
+                let Some((name, value)) = value.split_once('=') else {
+                    return Err("expected key=value".to_string());
+                };
+                if name.trim().is_empty() {
+                    return Err("property name is empty".to_string());
+                }
+                self.meta
+                    .properties
+                    .insert(name.trim().to_string(), infer_value(value.trim()));
+            }
+            _ => return Err(format!("{key:?} is not a level property")),
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, size: IVec2) -> Result<(), String> {
+        if size.min_element() < 1 {
+            return Err("the level needs at least one cell".to_string());
+        }
+        let grow = size - IVec2::new(self.bounds.width, self.bounds.height);
+        let shift = grow * self.anchor.weight() / 2;
+        self.bounds.x -= shift.x;
+        self.bounds.y -= shift.y;
+        self.bounds.width = size.x;
+        self.bounds.height = size.y;
+        self.pending_crop = self.crop;
+        Ok(())
+    }
+}
+
+pub fn level_property_fields(properties: &LevelProperties) -> Vec<Field> {
+    let bounds = &properties.bounds;
+    let meta = &properties.meta;
+    let mut fields = vec![
+        Field::editable(FieldKey::LevelName, "name", &meta.name),
+        Field::editable(FieldKey::LevelAuthor, "author", &meta.author),
+        Field::editable(FieldKey::LevelMusic, "music", &meta.music),
+        Field::editable(
+            FieldKey::LevelParTime,
+            "par time",
+            meta.par_time
+                .map(|time| time.to_string())
+                .unwrap_or_default(),
+        ),
+    ];
+    for (name, value) in &meta.properties {
+        fields.push(Field::editable(
+            FieldKey::LevelProperty(name.clone()),
+            &format!("{name} ({})", FieldType::of(value).label()),
+            display_value(value),
+        ));
+    }
+    fields.push(Field::editable(
+        FieldKey::NewLevelProperty,
+        "+ key=value",
+        "",
+    ));
+    fields.extend([
+        Field::editable(FieldKey::BoundsX, "origin x", bounds.x),
+        Field::editable(FieldKey::BoundsY, "origin y", bounds.y),
+        Field::editable(FieldKey::BoundsWidth, "width", bounds.width),
+        Field::editable(FieldKey::BoundsHeight, "height", bounds.height),
+        Field::editable(
+            FieldKey::Background,
+            "background",
+            Srgba::from(bounds.background_color()).to_hex(),
+        ),
+        Field::editable(
+            FieldKey::ResizeAnchor,
+            "resize anchor",
+            properties.anchor.label(),
+        ),
+        Field::editable(FieldKey::ResizeCrop, "crop on resize", properties.crop),
+    ]);
+    fields
+}
+
+#[derive(Component)]
+pub struct LevelPropertyPanel;
+
+// fills the bounds with the background color, behind the tiles
+#[derive(Component)]
+pub struct BoundsBackground;
+
+pub fn setup_level_property_panel(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(0.0),
+                bottom: Val::Px(40.0),
+                width: Val::Px(260.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(LevelPropertyPanel);
+    commands.spawn((SpriteBundle::default(), BoundsBackground));
+}
+
+pub fn toggle_level_property_panel(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut panel_q: Query<&mut Style, With<LevelPropertyPanel>>,
+) {
+    if !(keyboard_input.pressed(KeyCode::ControlLeft) && keyboard_input.just_pressed(KeyCode::KeyL))
+    {
+        return;
+    }
+    for mut style in &mut panel_q {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+}
+
+pub fn refresh_level_property_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    properties: Res<LevelProperties>,
+    edit: Res<FieldEdit>,
+    panel_q: Query<Entity, With<LevelPropertyPanel>>,
+) {
+    if !(properties.is_changed() || edit.is_changed()) {
+        return;
+    }
+    let Ok(panel) = panel_q.get_single() else {
+        return;
+    };
+    commands.entity(panel).despawn_descendants();
+    spawn_fields(
+        &mut commands,
+        panel,
+        "Level",
+        level_property_fields(&properties),
+        &edit,
+        &asset_server,
+    );
+}
+
+pub fn sync_bounds_background(
+    properties: Res<LevelProperties>,
+    mut background_q: Query<(&mut Sprite, &mut Transform), With<BoundsBackground>>,
+) {
+    if !properties.is_changed() {
+        return;
+    }
+    let rect = properties.bounds.world_rect();
+    for (mut sprite, mut transform) in &mut background_q {
+        sprite.color = properties.bounds.background_color();
+        sprite.custom_size = Some(rect.size());
+        transform.translation = rect.center().extend(BOUNDS_BACKGROUND_Z);
+    }
+}
+
+pub fn draw_bounds(mut gizmos: Gizmos, properties: Res<LevelProperties>) {
+    let rect = properties.bounds.world_rect();
+    gizmos.rect_2d(rect.center(), 0.0, rect.size(), PRESSED_BORDER);
+}
+
+// erases what a cropping resize left outside the bounds
+pub fn crop_to_bounds(
+    mut commands: Commands,
+    mut properties: ResMut<LevelProperties>,
+    level_q: Query<(Entity, &Transform, Has<Terrain>), With<LevelEntity>>,
+    mut terrain_changed_w: EventWriter<TerrainChanged>,
+) {
+    if !properties.pending_crop {
+        return;
+    }
+    properties.pending_crop = false;
+    for (ent, transform, is_terrain) in &level_q {
+        let cell = world_to_cell(transform.translation);
+        if !properties.bounds.contains(cell) {
+            commands.entity(ent).despawn_recursive();
+            // terrain left inside the bounds loses a neighbour, like erasing does
+            if is_terrain {
+                terrain_changed_w.send(TerrainChanged(cell));
+            }
+        }
+    }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    cursor_over_ui, entities::CustomEntity, inspector::Selection, level::CurrentLevel,
+    screen_to_world, world_to_cell, ClickAnd, HOVER_BORDER, PRESSED_BORDER,
+};
+
+const DANGLING_COLOR: Color = Color::srgb(0.9, 0.1, 0.1);
+
+// identifies a custom entity across save and load so links can point at it
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct EntityId(pub u64);
+
+/// The id the next placed custom entity gets. It only ever grows and is saved with the
+/// level, so an erased entity's id is never handed out again and links to it stay dangling.
+#[derive(Resource)]
+pub struct NextEntityId(pub u64);
+
+impl Default for NextEntityId {
+    fn default() -> Self {
+        NextEntityId(1)
+    }
+}
+
+// ref field of the selected entity waiting for a target click
+#[derive(Resource, Default)]
+pub struct PendingLink(pub Option<String>);
+
+// ref fields whose target no longer exists
+#[derive(Component)]
+pub struct DanglingLinks(pub Vec<String>);
+
+// gives newly placed entities the next unused id, loaded ones keep the id they were saved with
+pub fn assign_entity_ids(
+    mut commands: Commands,
+    new_q: Query<Entity, (With<CustomEntity>, Without<EntityId>)>,
+    mut next_id: ResMut<NextEntityId>,
+) {
+    for ent in &new_q {
+        commands.entity(ent).insert(EntityId(next_id.0));
+        next_id.0 += 1;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn edit_links(
+    mouse: Res<ButtonInput<MouseButton>>,
+    state: Res<State<ClickAnd>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    node_q: Query<(&GlobalTransform, &Node)>,
+    target_q: Query<(Entity, &Transform, &EntityId)>,
+    mut entity_q: Query<&mut CustomEntity>,
+    mut selection: ResMut<Selection>,
+    mut pending: ResMut<PendingLink>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    if *state.get() != ClickAnd::Link
+        || !mouse.any_just_pressed([MouseButton::Left, MouseButton::Right])
+    {
+        return;
+    }
+    let Some(cursor_pos) = window_q.single().cursor_position() else {
+        return;
+    };
+    if cursor_over_ui(cursor_pos, &node_q) {
+        return;
+    }
+    let (camera, camera_transform) = cam_q.single();
+    let cell = world_to_cell(screen_to_world(camera, camera_transform, cursor_pos));
+    let clicked = target_q
+        .iter()
+        .find(|(_, transform, _)| world_to_cell(transform.translation) == cell);
+
+    let source = selection.0.filter(|ent| entity_q.contains(*ent));
+    let (Some(source), Some(field)) = (source, pending.0.clone()) else {
+        // without an armed ref field a click picks whose links are being edited
+        if let Some((ent, ..)) = clicked.filter(|(ent, ..)| entity_q.contains(*ent)) {
+            selection.0 = Some(ent);
+        }
+        pending.0 = None;
+        return;
+    };
+
+    let target = match mouse.just_pressed(MouseButton::Left) {
+        true => match clicked {
+            Some((ent, _, id)) if ent != source => Some(id.0),
+            _ => return,
+        },
+        // right click unlinks
+        false => None,
+    };
+    if let Ok(mut entity) = entity_q.get_mut(source) {
+        entity.links.insert(field, target);
+        current_level.dirty = true;
+    }
+    pending.0 = None;
+}
+
+#[allow(clippy::type_complexity)]
+pub fn flag_dangling_links(
+    mut commands: Commands,
+    mut removed_ids: RemovedComponents<EntityId>,
+    changed_q: Query<(), Or<(Changed<CustomEntity>, Added<EntityId>)>>,
+    entity_q: Query<(Entity, &CustomEntity, Option<&DanglingLinks>)>,
+    id_q: Query<&EntityId>,
+) {
+    if removed_ids.read().count() == 0 && changed_q.is_empty() {
+        return;
+    }
+    let ids: Vec<u64> = id_q.iter().map(|id| id.0).collect();
+    for (ent, entity, flagged) in &entity_q {
+        let dangling: Vec<String> = entity
+            .links
+            .iter()
+            .filter(|(_, target)| target.is_some_and(|target| !ids.contains(&target)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        match (dangling.is_empty(), flagged) {
+            (true, Some(_)) => {
+                commands.entity(ent).remove::<DanglingLinks>();
+            }
+            (true, None) => {}
+            (false, Some(flagged)) if flagged.0 == dangling => {}
+            (false, _) => {
+                warn!(
+                    "{} has dangling links: {}",
+                    entity.kind,
+                    dangling.join(", ")
+                );
+                commands.entity(ent).insert(DanglingLinks(dangling));
+            }
+        }
+    }
+}
+
+pub fn draw_links(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    entity_q: Query<(Entity, &Transform, &CustomEntity)>,
+    id_q: Query<(&EntityId, &Transform)>,
+) {
+    let positions: HashMap<u64, Vec2> = id_q
+        .iter()
+        .map(|(id, transform)| (id.0, transform.translation.xy()))
+        .collect();
+    for (ent, transform, entity) in &entity_q {
+        let from = transform.translation.xy();
+        let color = match selection.0 == Some(ent) {
+            true => PRESSED_BORDER,
+            false => HOVER_BORDER.with_alpha(0.6),
+        };
+        for target in entity.links.values().flatten() {
+            match positions.get(target) {
+                Some(to) => {
+                    gizmos.arrow_2d(from, *to, color);
+                }
+                // the linked entity was erased
+                None => {
+                    gizmos.line_2d(
+                        from + Vec2::splat(-8.0),
+                        from + Vec2::splat(8.0),
+                        DANGLING_COLOR,
+                    );
+                    gizmos.line_2d(
+                        from + Vec2::new(-8.0, 8.0),
+                        from + Vec2::new(8.0, -8.0),
+                        DANGLING_COLOR,
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,15 +1,39 @@
-use std::path::Path;
-
+mod autotile;
+mod collision;
+mod dialog;
+mod entities;
+mod hazards;
+mod inspector;
+mod level;
+mod level_properties;
+mod links;
+mod mobs;
+mod patrol;
+mod playtest;
+mod project;
+mod recovery;
+mod regions;
+mod stats;
+mod status_bar;
+mod tile_properties;
+mod tileset;
+mod validation;
+mod world;
+
+use std::{collections::BTreeMap, f32::consts::FRAC_PI_2, path::Path};
+
+use autotile::{Terrain, TerrainBrushes, TerrainChanged};
 use bevy::{
     diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
-    input::{
-        keyboard::{Key, KeyboardInput},
-        ButtonState,
-    },
     prelude::*,
-    render::render_resource::encase::rts_array::Length,
     window::PrimaryWindow,
 };
+use dialog::{ClearCanvas, ClearScope, DialogAction, PendingAction};
+use entities::{CustomEntity, EntityRegistry};
+use hazards::{HazardKind, HazardRegistry};
+use level::{CurrentLevel, SaveLevel};
+use mobs::MobRegistry;
+use serde::{Deserialize, Serialize};
 
 const PANEL_COLOR: Color = Color::srgba(0.798, 0.506, 0.561, 0.3);
 const BORDER_COLOR: Color = Color::srgb(0.18, 0.176, 0.259);
@@ -21,6 +45,8 @@ const PRESSED_BORDER: Color = Color::srgb(0.988, 0.565, 0.239);
 enum AppState {
     LoadAssets,
     InLevelEdit,
+    Playtest,
+    WorldView,
 }
 
 #[derive(Resource)]
@@ -66,6 +92,10 @@ impl<T: Component> ColliderBundle<T> {
     }
 }
 
+// everything that belongs to the level itself, as opposed to UI, cameras or overlays
+#[derive(Component)]
+struct LevelEntity;
+
 #[derive(Component)]
 struct Player;
 
@@ -75,12 +105,47 @@ struct Tile(usize);
 #[derive(Component)]
 struct Hazard(usize);
 
-#[derive(Component)]
-struct Mob;
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+enum Facing {
+    Left,
+    #[default]
+    Right,
+}
+
+#[derive(Component, Clone)]
+struct Mob {
+    kind: String,
+    facing: Facing,
+    speed: f32,
+    health: i32,
+    properties: BTreeMap<String, String>,
+}
+
+#[derive(Component, Clone, Copy, Default, PartialEq)]
+struct TileOrientation {
+    flip_x: bool,
+    flip_y: bool,
+    // clockwise quarter turns
+    rotation: u8,
+}
+
+impl TileOrientation {
+    fn quat(&self) -> Quat {
+        Quat::from_rotation_z(-FRAC_PI_2 * self.rotation as f32)
+    }
+}
+
+#[derive(Resource, Default)]
+struct PaintOrientation(TileOrientation);
 
 #[derive(Resource)]
 struct SelectedTile(usize);
 
+// the held mouse button started as a pick, it must not paint until released
+#[derive(Resource, Default)]
+struct PickHeld(bool);
+
 #[derive(Component)]
 struct TileButton(usize);
 
@@ -89,8 +154,35 @@ enum ClickAnd {
     DrawTile,
     DrawHazard,
     DrawMob,
+    DrawTerrain,
     Erase,
     PlacePlayer,
+    Pick,
+    Select,
+    EditPath,
+    // index into EntityRegistry::types
+    DrawEntity(usize),
+    Link,
+    DrawRegion,
+}
+
+impl ClickAnd {
+    fn label(&self) -> &'static str {
+        match self {
+            ClickAnd::DrawTile => "Tile",
+            ClickAnd::DrawHazard => "Hazard",
+            ClickAnd::DrawMob => "Mob",
+            ClickAnd::DrawTerrain => "Terrain",
+            ClickAnd::Erase => "Erase",
+            ClickAnd::PlacePlayer => "Player",
+            ClickAnd::Pick => "Pick",
+            ClickAnd::Select => "Select",
+            ClickAnd::EditPath => "Path",
+            ClickAnd::DrawEntity(_) => "Entity",
+            ClickAnd::Link => "Link",
+            ClickAnd::DrawRegion => "Region",
+        }
+    }
 }
 
 #[derive(Component)]
@@ -98,8 +190,34 @@ enum ToolType {
     Tile,
     Hazard,
     Mob,
+    Terrain,
     Erase,
     Player,
+    Pick,
+    Select,
+    Path,
+    Link,
+    Region,
+    Custom(usize),
+}
+
+impl ToolType {
+    fn click_and(&self) -> ClickAnd {
+        match self {
+            ToolType::Tile => ClickAnd::DrawTile,
+            ToolType::Hazard => ClickAnd::DrawHazard,
+            ToolType::Mob => ClickAnd::DrawMob,
+            ToolType::Terrain => ClickAnd::DrawTerrain,
+            ToolType::Erase => ClickAnd::Erase,
+            ToolType::Player => ClickAnd::PlacePlayer,
+            ToolType::Pick => ClickAnd::Pick,
+            ToolType::Select => ClickAnd::Select,
+            ToolType::Path => ClickAnd::EditPath,
+            ToolType::Link => ClickAnd::Link,
+            ToolType::Region => ClickAnd::DrawRegion,
+            ToolType::Custom(index) => ClickAnd::DrawEntity(*index),
+        }
+    }
 }
 
 #[derive(Event, Clone, Copy)]
@@ -111,12 +229,13 @@ fn detect_inputs(
     mouse: Res<ButtonInput<MouseButton>>,
     mut event_writer: EventWriter<ClickEvent>,
     window_q: Query<&Window, With<PrimaryWindow>>,
+    mut pick_held: ResMut<PickHeld>,
 ) {
     if mouse.pressed(MouseButton::Left) {
         let cursor_pos = window_q.single().cursor_position().unwrap_or(Vec2::ZERO);
-        event_writer.send(ClickEvent {
-            cursor_pos: cursor_pos,
-        });
+        event_writer.send(ClickEvent { cursor_pos });
+    } else if pick_held.0 {
+        pick_held.0 = false;
     }
 }
 
@@ -129,168 +248,360 @@ fn check_ui_position(transform: &GlobalTransform, node: &Node) -> (Vec2, Vec2) {
     (min, max)
 }
 
+fn cursor_over_ui(cursor_pos: Vec2, node_q: &Query<(&GlobalTransform, &Node)>) -> bool {
+    node_q.iter().any(|(transform, node)| {
+        let (min, max) = check_ui_position(transform, node);
+        cursor_pos.x >= min.x
+            && cursor_pos.x <= max.x
+            && cursor_pos.y >= min.y
+            && cursor_pos.y <= max.y
+    })
+}
+
 fn screen_to_world(camera: &Camera, camera_transform: &GlobalTransform, screen_pos: Vec2) -> Vec3 {
     let size = Vec2::splat(24.0);
     let half_size = Vec2::splat(12.0);
 
     let world_pos = camera
-        .viewport_to_world_2d(camera_transform.into(), screen_pos)
+        .viewport_to_world_2d(camera_transform, screen_pos)
         .unwrap_or_default();
 
     let tile_pos = (world_pos / size.x).floor() * size.y + half_size;
     tile_pos.extend(1.0)
 }
 
+fn world_to_cell(pos: Vec3) -> IVec2 {
+    (pos.truncate() / 24.0).floor().as_ivec2()
+}
+
+fn cell_to_world(cell: IVec2) -> Vec3 {
+    (cell.as_vec2() * 24.0 + Vec2::splat(12.0)).extend(1.0)
+}
+
+fn tile_bundle(
+    pos: Vec3,
+    index: usize,
+    orientation: TileOrientation,
+    texture: Handle<Image>,
+    layout: Handle<TextureAtlasLayout>,
+) -> (SpriteBundle, TextureAtlas, TileOrientation, LevelEntity) {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(24.0)),
+                flip_x: orientation.flip_x,
+                flip_y: orientation.flip_y,
+                ..default()
+            },
+            transform: Transform::from_translation(pos).with_rotation(orientation.quat()),
+            texture,
+            ..default()
+        },
+        TextureAtlas { index, layout },
+        orientation,
+        LevelEntity,
+    )
+}
+
+fn player_bundle(pos: Vec3) -> (SpriteBundle, LevelEntity) {
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color: Color::WHITE,
+                custom_size: Some(Vec2::splat(24.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(pos),
+            ..default()
+        },
+        LevelEntity,
+    )
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 fn handle_mouse_click(
     mut commands: Commands,
     cam_q: Query<(&Camera, &GlobalTransform)>,
     mut click_event_r: EventReader<ClickEvent>,
     state: Res<State<ClickAnd>>,
     mut transform_set: ParamSet<(
-        Query<(&Transform, Entity), With<Sprite>>,
-        Query<&mut Transform, (With<Player>, Without<Tile>)>,
+        Query<(&Transform, Entity, Has<Terrain>), With<LevelEntity>>,
+        Query<(&mut Transform, &mut Collider), (With<Player>, Without<Tile>)>,
+        Query<&Transform, With<Tile>>,
+        Query<&Transform, With<CustomEntity>>,
+        Query<&Transform, With<Mob>>,
+        Query<&Transform, With<Hazard>>,
     )>,
-    ent: Query<Entity, (With<Player>, Without<Tile>)>,
     node_q: Query<(&GlobalTransform, &Node)>,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    tileset: Res<tileset::Tileset>,
     selected_tile: Res<SelectedTile>,
-    text_res: Res<TextInput>,
-    mut click_state: ResMut<ClickState>,
+    paint_orientation: Res<PaintOrientation>,
+    terrain_brushes: Res<TerrainBrushes>,
+    (mob_registry, hazard_registry, entity_registry, level_properties): (
+        Res<MobRegistry>,
+        Res<HazardRegistry>,
+        Res<EntityRegistry>,
+        Res<level_properties::LevelProperties>,
+    ),
+    mut terrain_changed_w: EventWriter<TerrainChanged>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut current_level: ResMut<CurrentLevel>,
+    pick_held: Res<PickHeld>,
 ) {
+    // alt-click is handled by pick_on_click, and so is the rest of a press that picked
+    if pick_held.0 || keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
     let size = Vec2::splat(24.0);
 
-    let texture = asset_server.load(text_res.0.clone());
-    let texture_atlas = TextureAtlasLayout::from_grid(UVec2::splat(24), 4, 4, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
+    let texture = &tileset.texture;
+    let texture_atlas_handle = &tileset.layout;
     let cam = cam_q.single();
+    let mut painted_tiles = Vec::new();
+    let mut painted_hazards = Vec::new();
+    let mut placed_entities = Vec::new();
+    let mut placed_mobs = Vec::new();
     for click_event in click_event_r
         .par_read()
         .batching_strategy(bevy::ecs::batching::BatchingStrategy::default())
     {
-        for (transform, node) in &node_q {
-            let min_max = check_ui_position(&transform, &node);
-            let min = min_max.0;
-            let max = min_max.1;
-
-            if click_event.0.cursor_pos.x >= min.x
-                && click_event.0.cursor_pos.x <= max.x
-                && click_event.0.cursor_pos.y >= min.y
-                && click_event.0.cursor_pos.y <= max.y
-            {
-                return;
-            }
+        if cursor_over_ui(click_event.0.cursor_pos, &node_q) {
+            return;
         }
         let click_pos = screen_to_world(cam.0, cam.1, click_event.0.cursor_pos);
+        // erasing still works outside the bounds so stray content can be cleaned up
+        if *state.get() != ClickAnd::Erase
+            && !level_properties.bounds.contains(world_to_cell(click_pos))
+        {
+            continue;
+        }
+        // holding the button keeps clicking, every tool places one thing per cell
+        let cell = world_to_cell(click_pos);
         match state.get() {
             ClickAnd::DrawTile => {
+                if painted_tiles.contains(&cell)
+                    || transform_set
+                        .p2()
+                        .iter()
+                        .any(|transform| world_to_cell(transform.translation) == cell)
+                {
+                    continue;
+                }
+                painted_tiles.push(cell);
                 commands
-                    .spawn((
-                        SpriteBundle {
-                            sprite: Sprite {
-                                custom_size: Some(size.clone()),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(click_pos),
-                            texture: texture.clone(),
-                            ..default()
-                        },
-                        TextureAtlas {
-                            index: selected_tile.0,
-                            layout: texture_atlas_handle.clone(),
-                        },
-                    ))
-                    .insert(ColliderBundle::new(
+                    .spawn(tile_bundle(
                         click_pos,
-                        size.clone(),
-                        Tile(selected_tile.0),
-                    ));
+                        selected_tile.0,
+                        paint_orientation.0,
+                        texture.clone(),
+                        texture_atlas_handle.clone(),
+                    ))
+                    .insert(ColliderBundle::new(click_pos, size, Tile(selected_tile.0)));
+                current_level.dirty = true;
             }
             ClickAnd::DrawHazard => {
+                if painted_hazards.contains(&cell)
+                    || transform_set
+                        .p5()
+                        .iter()
+                        .any(|transform| world_to_cell(transform.translation) == cell)
+                {
+                    continue;
+                }
+                painted_hazards.push(cell);
                 commands
-                    .spawn((
-                        SpriteBundle {
-                            sprite: Sprite {
-                                custom_size: Some(size.clone()),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(click_pos),
-                            texture: texture.clone(),
-                            ..default()
-                        },
-                        TextureAtlas {
-                            index: selected_tile.0,
-                            layout: texture_atlas_handle.clone(),
-                        },
+                    .spawn(tile_bundle(
+                        click_pos,
+                        selected_tile.0,
+                        paint_orientation.0,
+                        texture.clone(),
+                        texture_atlas_handle.clone(),
                     ))
                     .insert(ColliderBundle::new(
                         click_pos,
-                        size.clone(),
+                        size,
                         Hazard(selected_tile.0),
-                    ));
+                    ))
+                    .insert(HazardKind(hazard_registry.selected().name.clone()));
+                current_level.dirty = true;
             }
             ClickAnd::DrawMob => {
+                if placed_mobs.contains(&cell)
+                    || transform_set
+                        .p4()
+                        .iter()
+                        .any(|transform| world_to_cell(transform.translation) == cell)
+                {
+                    continue;
+                }
+                placed_mobs.push(cell);
+                mobs::spawn_mob(
+                    &mut commands,
+                    click_pos,
+                    mob_registry.selected().instance(),
+                    &mob_registry,
+                    texture,
+                    texture_atlas_handle,
+                );
+                current_level.dirty = true;
+            }
+            ClickAnd::DrawEntity(index) => {
+                let Some(entity_type) = entity_registry.types.get(*index) else {
+                    continue;
+                };
+                if placed_entities.contains(&cell)
+                    || transform_set
+                        .p3()
+                        .iter()
+                        .any(|transform| world_to_cell(transform.translation) == cell)
+                {
+                    continue;
+                }
+                placed_entities.push(cell);
+                entities::spawn_custom_entity(
+                    &mut commands,
+                    click_pos,
+                    entity_type.instance(),
+                    &entity_registry,
+                    texture,
+                    texture_atlas_handle,
+                );
+                current_level.dirty = true;
+            }
+            ClickAnd::DrawTerrain => {
+                let Some(rule_set) = terrain_brushes.active() else {
+                    continue;
+                };
+                // terrain shares the tile layer, it doesn't stack on a plain tile either
+                if painted_tiles.contains(&cell)
+                    || transform_set
+                        .p2()
+                        .iter()
+                        .any(|transform| world_to_cell(transform.translation) == cell)
+                {
+                    continue;
+                }
+                painted_tiles.push(cell);
+
                 commands
-                    .spawn(SpriteBundle {
-                        sprite: Sprite {
-                            color: Color::srgb(0.71, 0.075, 0.031),
-                            custom_size: Some(size.clone()),
-                            ..default()
-                        },
-                        transform: Transform::from_translation(click_pos),
-                        ..default()
-                    })
-                    .insert(ColliderBundle::new(click_pos, size.clone(), Mob));
+                    .spawn(tile_bundle(
+                        click_pos,
+                        rule_set.default_index,
+                        TileOrientation::default(),
+                        texture.clone(),
+                        texture_atlas_handle.clone(),
+                    ))
+                    .insert(ColliderBundle::new(
+                        click_pos,
+                        size,
+                        Tile(rule_set.default_index),
+                    ))
+                    .insert(Terrain(terrain_brushes.active));
+                terrain_changed_w.send(TerrainChanged(cell));
+                current_level.dirty = true;
             }
             ClickAnd::Erase => {
-                for (transform, entity) in &mut transform_set.p0().iter_mut() {
+                for (transform, entity, is_terrain) in &transform_set.p0() {
                     if transform.translation.xy() == click_pos.xy() {
                         commands.entity(entity).despawn();
+                        current_level.dirty = true;
+                        if is_terrain {
+                            terrain_changed_w.send(TerrainChanged(cell));
+                        }
                     }
                 }
             }
+            // these tools handle the mouse in their own systems
+            ClickAnd::Pick
+            | ClickAnd::Select
+            | ClickAnd::EditPath
+            | ClickAnd::Link
+            | ClickAnd::DrawRegion => {}
             ClickAnd::PlacePlayer => {
-                if let Some(ent) = ent.iter().next() {
-                    if let Ok(mut transform) = transform_set.p1().get_mut(ent) {
-                        transform.translation = click_pos;
-                    }
+                if let Some((mut transform, mut collider)) = transform_set.p1().iter_mut().next() {
+                    transform.translation = click_pos;
+                    collider.pos = click_pos;
                 } else {
                     commands
-                        .spawn(SpriteBundle {
-                            sprite: Sprite {
-                                color: Color::WHITE,
-                                custom_size: Some(size.clone()),
-                                ..default()
-                            },
-                            transform: Transform::from_translation(click_pos),
-                            ..default()
-                        })
-                        .insert(Collider {
-                            pos: click_pos,
-                            size: Vec2::splat(24.0),
-                        })
-                        .insert(Player);
+                        .spawn(player_bundle(click_pos))
+                        .insert(ColliderBundle::new(click_pos, size, Player));
                 }
+                current_level.dirty = true;
             }
         }
     }
 }
 
-fn setup_path_input_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
-    let node = NodeBundle {
-        style: Style {
-            width: Val::Px(200.0),
-            height: Val::Px(200.0),
-            align_self: AlignSelf::Center,
-            justify_self: JustifySelf::Center,
-            align_items: AlignItems::Center,
-            justify_items: JustifyItems::Center,
-            ..default()
-        },
-        background_color: BackgroundColor(PANEL_COLOR),
-        ..default()
+#[allow(clippy::too_many_arguments)]
+fn pick_on_click(
+    mut click_event_r: EventReader<ClickEvent>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<ClickAnd>>,
+    mut tool_state: ResMut<NextState<ClickAnd>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    node_q: Query<(&GlobalTransform, &Node)>,
+    tile_q: Query<(&Transform, &Tile, &TileOrientation, Option<&Terrain>)>,
+    hazard_q: Query<(&Transform, &Hazard, &TileOrientation, &HazardKind)>,
+    entity_q: Query<(&Transform, &CustomEntity)>,
+    entity_registry: Res<EntityRegistry>,
+    mut selected_tile: ResMut<SelectedTile>,
+    mut hazard_registry: ResMut<HazardRegistry>,
+    mut paint_orientation: ResMut<PaintOrientation>,
+    mut terrain_brushes: ResMut<TerrainBrushes>,
+    mut pick_held: ResMut<PickHeld>,
+) {
+    let Some(click_event) = click_event_r.read().last() else {
+        return;
     };
+    if *state.get() != ClickAnd::Pick
+        && !keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+    {
+        return;
+    }
+    if cursor_over_ui(click_event.cursor_pos, &node_q) {
+        return;
+    }
+    pick_held.0 = true;
+
+    let (camera, camera_transform) = cam_q.single();
+    let click_pos = screen_to_world(camera, camera_transform, click_event.cursor_pos);
+
+    let picked_entity = entity_q
+        .iter()
+        .find(|(transform, _)| transform.translation.xy() == click_pos.xy())
+        .and_then(|(_, entity)| entity_registry.position(&entity.kind));
 
+    if let Some(index) = picked_entity {
+        tool_state.set(ClickAnd::DrawEntity(index));
+    } else if let Some((_, hazard, orientation, kind)) = hazard_q
+        .iter()
+        .find(|(transform, ..)| transform.translation.xy() == click_pos.xy())
+    {
+        selected_tile.0 = hazard.0;
+        paint_orientation.0 = *orientation;
+        if let Some(index) = hazard_registry.position(&kind.0) {
+            hazard_registry.selected = index;
+        }
+        tool_state.set(ClickAnd::DrawHazard);
+    } else if let Some((_, tile, orientation, terrain)) = tile_q
+        .iter()
+        .find(|(transform, ..)| transform.translation.xy() == click_pos.xy())
+    {
+        selected_tile.0 = tile.0;
+        match terrain {
+            Some(terrain) => {
+                terrain_brushes.active = terrain.0;
+                tool_state.set(ClickAnd::DrawTerrain);
+            }
+            None => {
+                paint_orientation.0 = *orientation;
+                tool_state.set(ClickAnd::DrawTile);
+            }
+        }
+    }
+}
+
+fn setup_path_input_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
     let text_style = TextStyle {
         font: asset_server.load("../assets/FiraSans-Bold.ttf"),
         font_size: 25.0,
@@ -312,11 +623,11 @@ fn setup_path_input_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
         .insert(TextInputBox);
 }
 
-fn setup_cam(mut commands: Commands) {
-    commands.spawn(Camera2dBundle::default());
-}
-
-fn setup_tool_bar_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup_tool_bar_ui(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    entity_registry: Res<EntityRegistry>,
+) {
     let button = ButtonBundle {
         style: Style {
             width: Val::Px(60.0),
@@ -379,6 +690,13 @@ fn setup_tool_bar_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                 })
                 .insert(ToolType::Mob);
 
+            parent
+                .spawn(button.clone())
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section("Terrain", text_style.clone()));
+                })
+                .insert(ToolType::Terrain);
+
             parent
                 .spawn(button.clone())
                 .with_children(|p| {
@@ -392,6 +710,58 @@ fn setup_tool_bar_ui(mut commands: Commands, asset_server: Res<AssetServer>) {
                     p.spawn(TextBundle::from_section("Player", text_style.clone()));
                 })
                 .insert(ToolType::Player);
+
+            parent
+                .spawn(button.clone())
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section("Pick", text_style.clone()));
+                })
+                .insert(ToolType::Pick);
+
+            parent
+                .spawn(button.clone())
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section("Select", text_style.clone()));
+                })
+                .insert(ToolType::Select);
+
+            parent
+                .spawn(button.clone())
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section("Path", text_style.clone()));
+                })
+                .insert(ToolType::Path);
+
+            parent
+                .spawn(button.clone())
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section("Link", text_style.clone()));
+                })
+                .insert(ToolType::Link);
+
+            parent
+                .spawn(button.clone())
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section("Region", text_style.clone()));
+                })
+                .insert(ToolType::Region);
+
+            // one button per kind listed in assets/entities.json
+            for (index, entity_type) in entity_registry.types.iter().enumerate() {
+                parent
+                    .spawn(button.clone())
+                    .with_children(|p| {
+                        p.spawn(TextBundle::from_section(
+                            entity_type.name.clone(),
+                            TextStyle {
+                                font_size: 16.0,
+                                color: entity_type.tint(),
+                                ..text_style.clone()
+                            },
+                        ));
+                    })
+                    .insert(ToolType::Custom(index));
+            }
         });
 }
 fn setup_text_guide(mut commands: Commands, asset_server: Res<AssetServer>) {
@@ -404,12 +774,15 @@ fn setup_text_guide(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     let node = NodeBundle {
         style: Style {
-            width: Val::Px(400.0),
+            width: Val::Px(1020.0),
             height: Val::Px(100.0),
             align_self: AlignSelf::Start,
             justify_self: JustifySelf::Center,
             align_items: AlignItems::FlexStart,
             justify_content: JustifyContent::SpaceBetween,
+            // the hotkey list no longer fits one row of a default window
+            flex_wrap: FlexWrap::Wrap,
+            column_gap: Val::Px(16.0),
             ..default()
         },
         ..default()
@@ -425,7 +798,47 @@ fn setup_text_guide(mut commands: Commands, asset_server: Res<AssetServer>) {
             text_style.clone(),
         ));
         parent.spawn(TextBundle::from_section(
-            "clear canvas\nCTRL-r",
+            "clear canvas\nCTRL-r (+SHIFT tool)",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "open level\nCTRL-o",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "flip/rotate\nX Y R",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "pick tile\nALT-click",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "terrain brush\nT",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "export outlines\nCTRL-e",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "playtest\nCTRL-p",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "validate\nCTRL-v",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "level properties\nCTRL-l",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "level browser\nCTRL-b",
+            text_style.clone(),
+        ));
+        parent.spawn(TextBundle::from_section(
+            "world map\nCTRL-m",
             text_style.clone(),
         ));
         parent
@@ -555,44 +968,38 @@ fn text_input_system(
 }
 
 //tool bar interactions
+fn idle_border(selected: bool) -> BorderColor {
+    match selected {
+        true => BorderColor(PRESSED_BORDER),
+        false => BorderColor(BORDER_COLOR),
+    }
+}
+
+#[allow(clippy::type_complexity)]
 fn tool_button_interaction(
     mut interaction_q: Query<
         (&Interaction, &mut BorderColor, &ToolType),
         (Changed<Interaction>, With<Button>, Without<TileButton>),
     >,
+    state: Res<State<ClickAnd>>,
     mut tool_state: ResMut<NextState<ClickAnd>>,
 ) {
     for (interact, mut color, tool) in &mut interaction_q {
         match *interact {
             Interaction::Pressed => {
                 *color = BorderColor(PRESSED_BORDER);
-                match *tool {
-                    ToolType::Tile => {
-                        tool_state.set(ClickAnd::DrawTile);
-                    }
-                    ToolType::Hazard => {
-                        tool_state.set(ClickAnd::DrawHazard);
-                    }
-                    ToolType::Mob => {
-                        tool_state.set(ClickAnd::DrawMob);
-                    }
-                    ToolType::Erase => {
-                        tool_state.set(ClickAnd::Erase);
-                    }
-                    ToolType::Player => {
-                        tool_state.set(ClickAnd::PlacePlayer);
-                    }
-                }
+                tool_state.set(tool.click_and());
             }
             Interaction::Hovered => {
                 *color = BorderColor(HOVER_BORDER);
             }
             Interaction::None => {
-                *color = BorderColor(BORDER_COLOR);
+                *color = idle_border(tool.click_and() == *state.get());
             }
         }
     }
 }
+#[allow(clippy::type_complexity)]
 fn tile_selector_interaction(
     mut interaction_q: Query<
         (&Interaction, &mut BorderColor, &TileButton),
@@ -610,27 +1017,50 @@ fn tile_selector_interaction(
                 *color = BorderColor(HOVER_BORDER);
             }
             Interaction::None => {
-                *color = BorderColor(BORDER_COLOR);
+                *color = idle_border(tile_button.0 == tile_selected.0);
             }
         }
     }
 }
 
-fn setup_pop_up_tile_selector(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
-    text_res: Res<TextInput>,
+fn highlight_active_tool(
+    mut button_q: Query<(&ToolType, &Interaction, &mut BorderColor)>,
+    state: Res<State<ClickAnd>>,
+) {
+    for (tool, interaction, mut color) in &mut button_q {
+        let border = idle_border(tool.click_and() == *state.get());
+        if *interaction == Interaction::None && color.0 != border.0 {
+            *color = border;
+        }
+    }
+}
+
+fn highlight_selected_tile_button(
+    mut button_q: Query<(&TileButton, &Interaction, &mut BorderColor)>,
+    tile_selected: Res<SelectedTile>,
 ) {
-    let texture_handle = asset_server.load(text_res.0.clone());
-    let texture_atlas = TextureAtlasLayout::from_grid(UVec2::splat(24), 4, 4, None, None);
-    let texture_atlas_handle = texture_atlases.add(texture_atlas);
-    let atlas_length = texture_atlases
-        .get(&texture_atlas_handle)
-        .expect("failed to fetch texture")
-        .textures
-        .len();
+    for (tile_button, interaction, mut color) in &mut button_q {
+        let border = idle_border(tile_button.0 == tile_selected.0);
+        if *interaction == Interaction::None && color.0 != border.0 {
+            *color = border;
+        }
+    }
+}
+
+// sizes the selector grid to the tileset's columns and rows
+fn fit_tile_selector(style: &mut Style, grid: UVec2) {
+    style.width = Val::Px(230f32.max(grid.x as f32 * 30.0));
+    style.height = Val::Px(200f32.max(grid.y as f32 * 30.0));
+    style.grid_template_columns = RepeatedGridTrack::flex(grid.x as u16, 0.1);
+    style.grid_template_rows = RepeatedGridTrack::flex(grid.y as u16, 0.1);
+}
 
+fn spawn_tile_buttons(
+    parent: &mut ChildBuilder,
+    texture: &Handle<Image>,
+    layout: &Handle<TextureAtlasLayout>,
+    count: usize,
+) {
     let button = ButtonBundle {
         style: Style {
             width: Val::Px(24.0),
@@ -639,14 +1069,30 @@ fn setup_pop_up_tile_selector(
             justify_items: JustifyItems::Center,
             ..default()
         },
-        image: UiImage::new(texture_handle),
+        image: UiImage::new(texture.clone()),
         border_color: BorderColor(BORDER_COLOR),
         ..default()
     };
+    for index in 0..count {
+        parent
+            .spawn((
+                button.clone(),
+                TextureAtlas {
+                    index,
+                    layout: layout.clone(),
+                },
+            ))
+            .insert(TileButton(index));
+    }
+}
+
+fn setup_pop_up_tile_selector(
+    mut commands: Commands,
+    tileset: Res<tileset::Tileset>,
+    tileset_grid: Res<tileset::TilesetGrid>,
+) {
     let mut node = NodeBundle {
         style: Style {
-            width: Val::Px(230.0),
-            height: Val::Px(200.0),
             display: Display::None,
             align_self: AlignSelf::Center,
             justify_self: JustifySelf::Center,
@@ -654,32 +1100,23 @@ fn setup_pop_up_tile_selector(
             justify_items: JustifyItems::Center,
             align_content: AlignContent::Center,
             justify_content: JustifyContent::Center,
-
-            grid_template_columns: RepeatedGridTrack::flex(4, 0.1),
-            // Set the grid to have 4 rows all with sizes minmax(0, 1fr)
-            // This creates 4 exactly evenly sized rows
-            grid_template_rows: RepeatedGridTrack::flex(4, 0.1),
-
             ..default()
         },
         background_color: BackgroundColor(PANEL_COLOR),
         ..default()
     };
+    // one grid cell per tile, rebuilt by tileset::rebuild_tile_selector when the image changes
+    fit_tile_selector(&mut node.style, tileset_grid.0);
 
     commands
-        .spawn(node.clone())
+        .spawn(node)
         .with_children(|parent| {
-            for index in 0..atlas_length {
-                parent
-                    .spawn((
-                        button.clone(),
-                        TextureAtlas {
-                            index: index,
-                            layout: texture_atlas_handle.clone(),
-                        },
-                    ))
-                    .insert(TileButton(index));
-            }
+            spawn_tile_buttons(
+                parent,
+                &tileset.texture,
+                &tileset.layout,
+                tileset_grid.len(),
+            );
         })
         .insert(TileSelectionUi);
 }
@@ -702,27 +1139,101 @@ fn toggle_tile_selector(
 
 fn reset_on_key_input(
     keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut sprite_q: Query<Entity, With<Sprite>>,
-    mut commands: Commands,
+    state: Res<State<ClickAnd>>,
+    current_level: Res<CurrentLevel>,
+    mut pending: ResMut<PendingAction>,
+    mut clear_w: EventWriter<ClearCanvas>,
 ) {
-    if !keyboard_input.all_pressed([KeyCode::ControlLeft, KeyCode::KeyR]) {
+    if !(keyboard_input.pressed(KeyCode::ControlLeft) && keyboard_input.just_pressed(KeyCode::KeyR))
+        || pending.0.is_some()
+    {
         return;
     }
 
-    for ent in &sprite_q {
-        commands.entity(ent).despawn_recursive();
+    // shift limits the clear to whatever the active tool paints
+    let scope = match keyboard_input.pressed(KeyCode::ShiftLeft) {
+        false => ClearScope::All,
+        true => match state.get() {
+            ClickAnd::DrawTile | ClickAnd::DrawTerrain => ClearScope::Tiles,
+            ClickAnd::DrawHazard => ClearScope::Hazards,
+            ClickAnd::DrawMob => ClearScope::Mobs,
+            ClickAnd::DrawEntity(_) => ClearScope::Entities,
+            ClickAnd::DrawRegion => ClearScope::Regions,
+            _ => return,
+        },
+    };
+
+    match current_level.dirty {
+        true => pending.0 = Some(DialogAction::Clear(scope)),
+        false => {
+            clear_w.send(ClearCanvas(scope));
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn clear_canvas(
+    mut clear_r: EventReader<ClearCanvas>,
+    level_q: Query<
+        (
+            Entity,
+            Has<Tile>,
+            Has<Hazard>,
+            Has<Mob>,
+            Has<CustomEntity>,
+            Has<regions::Region>,
+        ),
+        With<LevelEntity>,
+    >,
+    mut commands: Commands,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    for ClearCanvas(scope) in clear_r.read() {
+        for (ent, is_tile, is_hazard, is_mob, is_entity, is_region) in &level_q {
+            let cleared = match scope {
+                ClearScope::All => true,
+                ClearScope::Tiles => is_tile,
+                ClearScope::Hazards => is_hazard,
+                ClearScope::Mobs => is_mob,
+                ClearScope::Entities => is_entity,
+                ClearScope::Regions => is_region,
+            };
+            if cleared {
+                commands.entity(ent).despawn_recursive();
+                current_level.dirty = true;
+            }
+        }
+    }
+}
+
+fn orientation_hotkeys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut paint_orientation: ResMut<PaintOrientation>,
+) {
+    if keyboard_input.pressed(KeyCode::ControlLeft) {
+        return;
+    }
+    let orientation = &mut paint_orientation.0;
+    if keyboard_input.just_pressed(KeyCode::KeyX) {
+        orientation.flip_x = !orientation.flip_x;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyY) {
+        orientation.flip_y = !orientation.flip_y;
+    }
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        orientation.rotation = (orientation.rotation + 1) % 4;
     }
 }
 
 fn camera_movemovent(
-    mut camera_q: Query<&mut Transform, With<Camera>>,
+    mut camera_q: Query<(&mut Transform, &OrthographicProjection), With<Camera>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     time: Res<Time>,
 ) {
     if !keyboard_input.any_pressed([KeyCode::KeyW, KeyCode::KeyA, KeyCode::KeyS, KeyCode::KeyD]) {
         return;
     }
-    let mut transform = camera_q.single_mut();
+    let (mut transform, projection) = camera_q.single_mut();
     let mut direction = Vec2::ZERO;
 
     if keyboard_input.pressed(KeyCode::KeyW) {
@@ -738,48 +1249,11 @@ fn camera_movemovent(
         direction.x += 1.;
     }
 
-    let move_delta = direction.normalize_or_zero() * 300.0 * time.delta_seconds();
+    let move_delta =
+        direction.normalize_or_zero() * 300.0 * projection.scale * time.delta_seconds();
     transform.translation += move_delta.extend(0.);
 }
 
-/*
-fn save_level(
-    mut level_set: ParamSet<(
-        Query<(&Transform, &Sprite), With<Player>>,
-        Query<(&Transform, &Sprite), With<Tile>>,
-    )>,
-    key_pressed: Res<ButtonInput<KeyCode>>,
-) {
-    if key_pressed.pressed(KeyCode::ControlLeft) && key_pressed.just_pressed(KeyCode::KeyS) {
-        let mut tiles: Vec<TileData> = Vec::new();
-        let mut player_data = PlayerData {
-            pos: Vec3::ZERO,
-            size: Vec2::ZERO,
-        };
-
-        //this code queries tile positions, and player position and then serializes that data to json
-
-        for (player_transform, player_sprite) in level_set.p0().iter() {
-            player_data.pos = player_transform.translation;
-            player_data.size = player_sprite.custom_size.unwrap_or(Vec2::splat(24.0));
-        }
-
-        for (tile_transform, tile_sprite) in level_set.p1().iter() {
-            tiles.push(TileData {
-                pos: tile_transform.translation,
-                size: tile_sprite.custom_size.unwrap_or(Vec2::splat(24.0)),
-            });
-        }
-
-        let level = LevelData {
-            player_data,
-            tile_data: tiles,
-        };
-        let json = serde_json::to_string_pretty(&level).expect("failed tp serialize");
-        std::fs::write("level.json", json).expect("Failed to write level to file");
-    }
-}*/
-
 fn despawn_path_input(mut commands: Commands, mut input_q: Query<Entity, With<TextInputBox>>) {
     for ent in &mut input_q {
         commands.entity(ent).despawn();
@@ -787,23 +1261,107 @@ fn despawn_path_input(mut commands: Commands, mut input_q: Query<Entity, With<Te
 }
 
 fn main() {
+    // --stats <level.json> prints a summary of the level instead of opening the editor
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().position(|arg| arg == "--stats") {
+        let Some(path) = args.get(index + 1) else {
+            eprintln!("usage: --stats <level.json>");
+            std::process::exit(2);
+        };
+        if let Err(err) = stats::print_stats(Path::new(path)) {
+            eprintln!("failed to read {path}: {err}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     App::new()
-        .add_plugins((DefaultPlugins, FrameTimeDiagnosticsPlugin))
+        .add_plugins((
+            DefaultPlugins
+                .set(WindowPlugin {
+                    // closing goes through dialog::handle_close_requested
+                    close_when_requested: false,
+                    ..default()
+                })
+                .set(AssetPlugin {
+                    // a tileset saved by an artist reloads while the level is open
+                    watch_for_changes_override: Some(true),
+                    ..default()
+                }),
+            FrameTimeDiagnosticsPlugin,
+        ))
         .insert_state(AppState::LoadAssets)
         .add_event::<ClickEvent>()
+        .add_event::<TerrainChanged>()
+        .add_event::<SaveLevel>()
+        .add_event::<level::OpenLevel>()
+        .add_event::<ClearCanvas>()
+        .add_event::<validation::ValidateLevel>()
+        .add_event::<dialog::ResolvePending>()
+        .init_resource::<PendingAction>()
+        .init_resource::<TerrainBrushes>()
         .insert_resource(TextInput(String::new()))
         .insert_resource(Visible(false))
         .insert_resource(SelectedTile(0))
+        .init_resource::<PaintOrientation>()
+        .init_resource::<PickHeld>()
+        .init_resource::<CurrentLevel>()
+        .init_resource::<recovery::AutosaveTimer>()
+        .init_resource::<MobRegistry>()
+        .init_resource::<HazardRegistry>()
+        .init_resource::<EntityRegistry>()
+        .init_resource::<inspector::Selection>()
+        .init_resource::<inspector::FieldEdit>()
+        .init_resource::<links::PendingLink>()
+        .init_resource::<links::NextEntityId>()
+        .init_resource::<regions::ActiveRegionKind>()
+        .init_resource::<regions::RegionResize>()
+        .init_resource::<collision::CollisionExport>()
+        .init_resource::<tile_properties::TileProperties>()
+        .init_resource::<level_properties::LevelProperties>()
+        .init_resource::<project::Project>()
+        .init_resource::<validation::ValidationRules>()
+        .init_resource::<validation::ValidationReport>()
+        .init_resource::<world::WorldDragState>()
+        .init_resource::<tileset::TilesetGrid>()
         .insert_resource(ClickState::FirstClick)
         .insert_state(ClickAnd::DrawTile)
-        .add_systems(Startup, setup_path_input_ui)
         .add_systems(
-            OnEnter(AppState::InLevelEdit),
+            Startup,
+            (
+                setup_path_input_ui,
+                project::load_project,
+                recovery::setup_recovery_prompt.after(project::load_project),
+            ),
+        )
+        .add_systems(
+            // not OnEnter, coming back from a playtest must not set the editor up again
+            OnTransition {
+                exited: AppState::LoadAssets,
+                entered: AppState::InLevelEdit,
+            },
             (
-                setup_pop_up_tile_selector,
-                setup_tool_bar_ui,
+                tileset::setup_tileset,
+                setup_pop_up_tile_selector.after(tileset::setup_tileset),
+                setup_tool_bar_ui.after(entities::load_entity_registry),
+                entities::load_entity_registry,
                 setup_text_guide,
                 despawn_path_input,
+                status_bar::setup_status_bar.after(tileset::setup_tileset),
+                mobs::load_mob_registry,
+                mobs::setup_mob_palette.after(mobs::load_mob_registry),
+                hazards::load_hazard_registry,
+                hazards::setup_hazard_palette.after(hazards::load_hazard_registry),
+                regions::setup_region_palette,
+                tile_properties::setup_tile_property_panel,
+                validation::setup_validation_panel,
+                level_properties::setup_level_property_panel,
+                project::setup_level_browser,
+                recovery::restore_pending_level
+                    .after(mobs::load_mob_registry)
+                    .after(entities::load_entity_registry)
+                    .after(tileset::setup_tileset),
+                inspector::setup_inspector,
             ),
         )
         .add_systems(
@@ -813,18 +1371,220 @@ fn main() {
                 tile_selector_interaction.run_if(in_state(AppState::InLevelEdit)),
                 toggle_tile_selector.run_if(in_state(AppState::InLevelEdit)),
                 fps_debug_text_system.run_if(in_state(AppState::InLevelEdit)),
+                autotile::cycle_terrain_brush
+                    .run_if(in_state(AppState::InLevelEdit).and_then(not(inspector::is_editing))),
+                orientation_hotkeys
+                    .run_if(in_state(AppState::InLevelEdit).and_then(not(inspector::is_editing))),
+                level::load_level.run_if(in_state(AppState::InLevelEdit)),
+                highlight_selected_tile_button.run_if(in_state(AppState::InLevelEdit)),
+                highlight_active_tool.run_if(in_state(AppState::InLevelEdit)),
+                status_bar::update_status_bar.run_if(in_state(AppState::InLevelEdit)),
+                status_bar::update_status_info.run_if(in_state(AppState::InLevelEdit)),
                 text_input_system.run_if(in_state(AppState::LoadAssets)),
+                recovery::recovery_prompt_interaction.run_if(in_state(AppState::LoadAssets)),
+                recovery::autosave.run_if(in_state(AppState::InLevelEdit)),
+                collision::toggle_outline_export.run_if(in_state(AppState::InLevelEdit)),
+                playtest::start_playtest
+                    .run_if(in_state(AppState::InLevelEdit).and_then(not(inspector::is_editing))),
+            ),
+        )
+        .add_systems(OnEnter(AppState::Playtest), playtest::enter_playtest)
+        .add_systems(OnExit(AppState::Playtest), playtest::exit_playtest)
+        .add_systems(OnEnter(AppState::WorldView), world::enter_world_view)
+        .add_systems(OnExit(AppState::WorldView), world::exit_world_view)
+        .add_systems(
+            Update,
+            (
+                world::stop_world_view,
+                (world::drag_levels, world::draw_world).chain(),
+            )
+                .run_if(in_state(AppState::WorldView)),
+        )
+        .add_systems(
+            Update,
+            (
+                playtest::stop_playtest,
+                (
+                    playtest::move_body,
+                    playtest::respawn_on_death,
+                    playtest::follow_body,
+                )
+                    .chain(),
+                playtest::move_mobs,
+            )
+                .run_if(in_state(AppState::Playtest)),
+        )
+        .add_systems(
+            Update,
+            (
+                reset_on_key_input,
+                dialog::unsaved_dialog_interaction,
+                level::save_level,
+                dialog::resolve_pending_action,
+                clear_canvas,
+            )
+                .chain()
+                .run_if(in_state(AppState::InLevelEdit)),
+        )
+        .add_systems(
+            Update,
+            (
+                mobs::toggle_mob_palette,
+                mobs::mob_palette_interaction,
+                mobs::sync_mob_sprites,
+                hazards::toggle_hazard_palette,
+                hazards::hazard_palette_interaction,
+                hazards::tint_hazards,
+                (
+                    inspector::inspector_field_interaction,
+                    inspector::inspector_text_input,
+                    inspector::refresh_inspector,
+                    tile_properties::refresh_tile_property_panel,
+                    level_properties::refresh_level_property_panel,
+                )
+                    .chain(),
+                (
+                    autotile::load_terrain_rules,
+                    tile_properties::load_tile_properties,
+                    tile_properties::save_tile_properties,
+                )
+                    .chain()
+                    .after(level::load_level),
+                inspector::draw_selection,
+                patrol::edit_patrol_path,
+                patrol::draw_patrol_paths,
+                links::assign_entity_ids,
+                links::edit_links,
+                links::flag_dangling_links,
+                links::draw_links,
+            )
+                .run_if(in_state(AppState::InLevelEdit)),
+        )
+        .add_systems(
+            Update,
+            (
+                regions::toggle_region_palette,
+                regions::region_palette_interaction,
+                (
+                    regions::edit_regions,
+                    regions::spawn_drawn_region,
+                    regions::sync_region_sprites,
+                )
+                    .chain(),
+                regions::draw_regions,
+                validation::request_validation.run_if(not(inspector::is_editing)),
+                (
+                    validation::validate_level,
+                    validation::refresh_validation_panel,
+                )
+                    .chain()
+                    .after(level::save_level),
+                validation::issue_button_interaction,
+                level_properties::toggle_level_property_panel,
+                level_properties::sync_bounds_background,
+                level_properties::draw_bounds,
+                level_properties::crop_to_bounds,
+                project::toggle_level_browser,
+                project::refresh_level_browser,
+                project::level_browser_interaction,
+                project::track_current_level,
+                world::start_world_view.run_if(not(inspector::is_editing)),
+                (tileset::rebuild_tile_selector, tileset::flag_missing_tiles)
+                    .chain()
+                    .after(tileset::apply_tileset_size),
+                tileset::draw_missing_tiles,
+            )
+                .run_if(in_state(AppState::InLevelEdit)),
+        )
+        .add_systems(
+            Update,
+            (
+                dialog::handle_close_requested,
+                tileset::apply_tileset_size,
+                dialog::sync_unsaved_dialog.run_if(resource_changed::<PendingAction>),
             ),
         )
         .add_systems(
             FixedUpdate,
             (
                 detect_inputs,
-                handle_mouse_click,
-                reset_on_key_input,
-                camera_movemovent,
+                (handle_mouse_click, autotile::update_autotiles).chain(),
+                pick_on_click,
+                inspector::select_on_click,
+                camera_movemovent.run_if(not(inspector::is_editing)),
             )
                 .run_if(in_state(AppState::InLevelEdit)),
         )
+        .add_systems(
+            FixedUpdate,
+            camera_movemovent.run_if(in_state(AppState::WorldView)),
+        )
         .run();
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::state::app::StatesPlugin;
+
+    use super::*;
+
+    fn asset_counts(app: &App) -> (usize, usize) {
+        (
+            app.world().resource::<Assets<Image>>().len(),
+            app.world().resource::<Assets<TextureAtlasLayout>>().len(),
+        )
+    }
+
+    #[test]
+    fn painting_reuses_the_tileset_assets() {
+        let mut app = App::new();
+        // there is no assets folder to watch next to the tests
+        let assets = AssetPlugin {
+            watch_for_changes_override: Some(false),
+            ..default()
+        };
+        app.add_plugins((MinimalPlugins, assets, StatesPlugin))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .insert_state(ClickAnd::DrawTile)
+            .add_event::<ClickEvent>()
+            .add_event::<TerrainChanged>()
+            .insert_resource(TextInput("tiles.png".to_string()))
+            .insert_resource(SelectedTile(0))
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<PaintOrientation>()
+            .init_resource::<PickHeld>()
+            .init_resource::<TerrainBrushes>()
+            .init_resource::<MobRegistry>()
+            .init_resource::<HazardRegistry>()
+            .init_resource::<EntityRegistry>()
+            .init_resource::<level_properties::LevelProperties>()
+            .init_resource::<CurrentLevel>()
+            .init_resource::<tileset::TilesetGrid>()
+            .add_systems(Startup, tileset::setup_tileset)
+            .add_systems(Update, handle_mouse_click);
+        app.world_mut().spawn(Camera2dBundle::default());
+        app.update();
+        let before = asset_counts(&app);
+
+        // idle ticks and painting both draw from the one tileset layout, and holding the
+        // button over one cell paints it once
+        for frame in 0..10 {
+            if frame % 2 == 0 {
+                app.world_mut().send_event(ClickEvent {
+                    cursor_pos: Vec2::ZERO,
+                });
+            }
+            app.update();
+        }
+
+        assert_eq!(asset_counts(&app), before);
+        let layout = app.world().resource::<tileset::Tileset>().layout.clone();
+        let mut tile_q = app
+            .world_mut()
+            .query_filtered::<&TextureAtlas, With<Tile>>();
+        let atlases: Vec<&TextureAtlas> = tile_q.iter(app.world()).collect();
+        assert_eq!(atlases.len(), 1);
+        assert!(atlases.iter().all(|atlas| atlas.layout == layout));
+    }
+}
@@ -0,0 +1,236 @@
+use std::{collections::BTreeMap, path::Path};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    idle_border, patrol::Patrol, ClickAnd, ColliderBundle, Facing, LevelEntity, Mob, BORDER_COLOR,
+    BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR, PRESSED_BORDER,
+};
+
+/// A placeable mob kind, read from `assets/mobs.json`. Mobs with an `atlas_index` are
+/// drawn from the tileset, the rest as a square in `color`.
+#[derive(Deserialize, Clone)]
+pub struct MobType {
+    pub name: String,
+    #[serde(default)]
+    pub atlas_index: Option<usize>,
+    #[serde(default = "default_mob_color")]
+    pub color: [f32; 3],
+    #[serde(default)]
+    pub speed: f32,
+    #[serde(default)]
+    pub health: i32,
+    #[serde(default)]
+    pub properties: BTreeMap<String, String>,
+}
+
+fn default_mob_color() -> [f32; 3] {
+    [0.71, 0.075, 0.031]
+}
+
+impl MobType {
+    pub fn instance(&self) -> Mob {
+        Mob {
+            kind: self.name.clone(),
+            facing: Facing::default(),
+            speed: self.speed,
+            health: self.health,
+            properties: self.properties.clone(),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct MobRegistry {
+    pub types: Vec<MobType>,
+    pub selected: usize,
+}
+
+impl Default for MobRegistry {
+    fn default() -> Self {
+        Self {
+            types: vec![MobType {
+                name: "mob".to_string(),
+                atlas_index: None,
+                color: default_mob_color(),
+                speed: 1.0,
+                health: 1,
+                properties: BTreeMap::new(),
+            }],
+            selected: 0,
+        }
+    }
+}
+
+impl MobRegistry {
+    pub fn get(&self, name: &str) -> &MobType {
+        self.types
+            .iter()
+            .find(|mob_type| mob_type.name == name)
+            .unwrap_or(&self.types[0])
+    }
+
+    pub fn selected(&self) -> &MobType {
+        &self.types[self.selected]
+    }
+}
+
+#[derive(Component)]
+pub struct MobPalette;
+
+#[derive(Component)]
+pub struct MobTypeButton(pub usize);
+
+pub fn load_mob_registry(mut registry: ResMut<MobRegistry>) {
+    let path = Path::new("assets").join("mobs.json");
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        info!("no mob types at {}", path.display());
+        return;
+    };
+    match serde_json::from_str::<Vec<MobType>>(&json) {
+        Ok(types) if !types.is_empty() => {
+            registry.types = types;
+            registry.selected = 0;
+        }
+        Ok(_) => warn!("{} lists no mob types", path.display()),
+        Err(err) => warn!("failed to parse {}: {err}", path.display()),
+    }
+}
+
+pub fn spawn_mob(
+    commands: &mut Commands,
+    pos: Vec3,
+    mob: Mob,
+    registry: &MobRegistry,
+    texture: &Handle<Image>,
+    layout: &Handle<TextureAtlasLayout>,
+) -> Entity {
+    let mob_type = registry.get(&mob.kind);
+    let [r, g, b] = mob_type.color;
+    let color = match mob_type.atlas_index {
+        Some(_) => Color::WHITE,
+        None => Color::srgb(r, g, b),
+    };
+    let mut ent = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color,
+                custom_size: Some(Vec2::splat(24.0)),
+                flip_x: mob.facing == Facing::Left,
+                ..default()
+            },
+            transform: Transform::from_translation(pos),
+            ..default()
+        },
+        LevelEntity,
+    ));
+    if let Some(index) = mob_type.atlas_index {
+        ent.insert((
+            texture.clone(),
+            TextureAtlas {
+                index,
+                layout: layout.clone(),
+            },
+        ));
+    }
+    ent.insert(ColliderBundle::new(pos, Vec2::splat(24.0), mob))
+        .insert(Patrol::default());
+    ent.id()
+}
+
+pub fn sync_mob_sprites(mut mob_q: Query<(&Mob, &mut Sprite), Changed<Mob>>) {
+    for (mob, mut sprite) in &mut mob_q {
+        let flip_x = mob.facing == Facing::Left;
+        if sprite.flip_x != flip_x {
+            sprite.flip_x = flip_x;
+        }
+    }
+}
+
+pub fn setup_mob_palette(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    registry: Res<MobRegistry>,
+) {
+    let button = ButtonBundle {
+        style: Style {
+            height: Val::Px(32.0),
+            padding: UiRect::horizontal(Val::Px(6.0)),
+            align_items: AlignItems::Center,
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        border_color: BorderColor(BORDER_COLOR),
+        ..default()
+    };
+    let text_style = TextStyle {
+        font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+        font_size: 20.0,
+        color: HOVER_BORDER,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(88.0),
+                bottom: Val::Px(40.0),
+                column_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(MobPalette)
+        .with_children(|parent| {
+            for (index, mob_type) in registry.types.iter().enumerate() {
+                parent
+                    .spawn(button.clone())
+                    .with_children(|p| {
+                        p.spawn(TextBundle::from_section(
+                            mob_type.name.clone(),
+                            text_style.clone(),
+                        ));
+                    })
+                    .insert(MobTypeButton(index));
+            }
+        });
+}
+
+pub fn toggle_mob_palette(
+    state: Res<State<ClickAnd>>,
+    mut palette_q: Query<&mut Style, With<MobPalette>>,
+) {
+    let display = match state.get() {
+        ClickAnd::DrawMob => Display::Flex,
+        _ => Display::None,
+    };
+    for mut style in &mut palette_q {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+pub fn mob_palette_interaction(
+    mut button_q: Query<(&Interaction, &mut BorderColor, &MobTypeButton)>,
+    mut registry: ResMut<MobRegistry>,
+) {
+    for (interaction, mut color, mob_button) in &mut button_q {
+        if *interaction == Interaction::Pressed && registry.selected != mob_button.0 {
+            registry.selected = mob_button.0;
+        }
+        let border = match *interaction {
+            Interaction::Pressed => BorderColor(PRESSED_BORDER),
+            Interaction::Hovered => BorderColor(HOVER_BORDER),
+            Interaction::None => idle_border(mob_button.0 == registry.selected),
+        };
+        if color.0 != border.0 {
+            *color = border;
+        }
+    }
+}
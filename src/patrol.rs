@@ -0,0 +1,114 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cell_to_world, cursor_over_ui, inspector::Selection, level::CurrentLevel, screen_to_world,
+    world_to_cell, ClickAnd, Mob, HOVER_BORDER, PRESSED_BORDER,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PatrolMode {
+    #[default]
+    Loop,
+    PingPong,
+}
+
+impl PatrolMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PatrolMode::Loop => "loop",
+            PatrolMode::PingPong => "ping-pong",
+        }
+    }
+}
+
+// waypoint cells a mob walks through, starting from where it was placed
+#[derive(Component, Clone, Default)]
+pub struct Patrol {
+    pub waypoints: Vec<IVec2>,
+    pub mode: PatrolMode,
+}
+
+impl Patrol {
+    // the full route in world space, including the mob's own cell
+    pub fn route(&self, start: Vec3) -> Vec<Vec2> {
+        std::iter::once(start.truncate())
+            .chain(
+                self.waypoints
+                    .iter()
+                    .map(|cell| cell_to_world(*cell).truncate()),
+            )
+            .collect()
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn edit_patrol_path(
+    mouse: Res<ButtonInput<MouseButton>>,
+    state: Res<State<ClickAnd>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    node_q: Query<(&GlobalTransform, &Node)>,
+    mob_q: Query<(Entity, &Transform), With<Mob>>,
+    mut patrol_q: Query<&mut Patrol>,
+    mut selection: ResMut<Selection>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    if *state.get() != ClickAnd::EditPath
+        || !mouse.any_just_pressed([MouseButton::Left, MouseButton::Right])
+    {
+        return;
+    }
+    let Some(cursor_pos) = window_q.single().cursor_position() else {
+        return;
+    };
+    if cursor_over_ui(cursor_pos, &node_q) {
+        return;
+    }
+    let (camera, camera_transform) = cam_q.single();
+    let cell = world_to_cell(screen_to_world(camera, camera_transform, cursor_pos));
+
+    // clicking a mob picks whose path is being edited
+    if let Some((ent, _)) = mob_q
+        .iter()
+        .find(|(_, transform)| world_to_cell(transform.translation) == cell)
+    {
+        selection.0 = Some(ent);
+        return;
+    }
+    let Some(Ok(mut patrol)) = selection.0.map(|ent| patrol_q.get_mut(ent)) else {
+        return;
+    };
+
+    if mouse.just_pressed(MouseButton::Left) {
+        patrol.waypoints.push(cell);
+    } else if patrol.waypoints.pop().is_none() {
+        return;
+    }
+    current_level.dirty = true;
+}
+
+pub fn draw_patrol_paths(
+    mut gizmos: Gizmos,
+    selection: Res<Selection>,
+    patrol_q: Query<(Entity, &Transform, &Patrol)>,
+) {
+    for (ent, transform, patrol) in &patrol_q {
+        if patrol.waypoints.is_empty() {
+            continue;
+        }
+        let color = match selection.0 == Some(ent) {
+            true => PRESSED_BORDER,
+            false => HOVER_BORDER.with_alpha(0.5),
+        };
+        let route = patrol.route(transform.translation);
+        gizmos.linestrip_2d(route.iter().copied(), color);
+        if patrol.mode == PatrolMode::Loop {
+            gizmos.line_2d(route[route.len() - 1], route[0], color);
+        }
+        for point in &route[1..] {
+            gizmos.circle_2d(*point, 4.0, color);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+use bevy::prelude::*;
+
+use crate::{
+    patrol::{Patrol, PatrolMode},
+    regions::{Region, RegionKind},
+    tile_properties::{CollisionType, TileProperties},
+    AppState, Collider, Hazard, Mob, Player, Tile,
+};
+
+const GRAVITY: f32 = -1400.0;
+const MAX_FALL_SPEED: f32 = 700.0;
+const RUN_SPEED: f32 = 180.0;
+const JUMP_SPEED: f32 = 480.0;
+const BODY_SIZE: Vec2 = Vec2::new(18.0, 22.0);
+const BODY_COLOR: Color = Color::srgb(0.988, 0.565, 0.239);
+// how far below the lowest tile the body may fall before it counts as a death
+const FALL_LIMIT: f32 = 20.0 * 24.0;
+
+// the controllable character, only exists while playtesting
+#[derive(Component, Default)]
+pub struct PlaytestBody {
+    velocity: Vec2,
+    grounded: bool,
+}
+
+// spawned for the playtest and despawned when it ends
+#[derive(Component)]
+pub struct PlaytestEntity;
+
+// where a mob or the camera was before the playtest moved it
+#[derive(Component)]
+pub struct PlaytestOrigin(Transform);
+
+// editor UI and the player marker are hidden while playtesting, this keeps what they were
+#[derive(Component)]
+pub struct PlaytestHidden(Visibility);
+
+// the route point a patrolling mob is walking towards
+#[derive(Component)]
+pub struct PatrolProgress {
+    next: usize,
+    forward: bool,
+}
+
+pub fn start_playtest(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    player_q: Query<(), With<Player>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if !(keyboard_input.pressed(KeyCode::ControlLeft) && keyboard_input.just_pressed(KeyCode::KeyP))
+    {
+        return;
+    }
+    if player_q.is_empty() {
+        warn!("place the player before playtesting");
+        return;
+    }
+    app_state.set(AppState::Playtest);
+}
+
+pub fn stop_playtest(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_state.set(AppState::InLevelEdit);
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn enter_playtest(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    player_q: Query<(Entity, &Transform, &Visibility), With<Player>>,
+    ui_q: Query<(Entity, &Visibility), (With<Node>, Without<Parent>)>,
+    moved_q: Query<(Entity, &Transform), Or<(With<Mob>, With<Camera>)>>,
+    patrol_q: Query<Entity, With<Patrol>>,
+) {
+    let Ok((player, spawn, visibility)) = player_q.get_single() else {
+        return;
+    };
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: BODY_COLOR,
+                custom_size: Some(BODY_SIZE),
+                ..default()
+            },
+            transform: Transform::from_translation(spawn.translation.truncate().extend(5.0)),
+            ..default()
+        },
+        PlaytestBody::default(),
+        PlaytestEntity,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "playtest - ESC to return",
+            TextStyle {
+                font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+                font_size: 20.0,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Px(8.0),
+            ..default()
+        }),
+        PlaytestEntity,
+    ));
+
+    commands
+        .entity(player)
+        .insert((PlaytestHidden(*visibility), Visibility::Hidden));
+    for (ent, visibility) in &ui_q {
+        commands
+            .entity(ent)
+            .insert((PlaytestHidden(*visibility), Visibility::Hidden));
+    }
+    for (ent, transform) in &moved_q {
+        commands.entity(ent).insert(PlaytestOrigin(*transform));
+    }
+    for ent in &patrol_q {
+        commands.entity(ent).insert(PatrolProgress {
+            next: 1,
+            forward: true,
+        });
+    }
+}
+
+// puts everything back the way the editor left it
+pub fn exit_playtest(
+    mut commands: Commands,
+    spawned_q: Query<Entity, With<PlaytestEntity>>,
+    mut origin_q: Query<(Entity, &mut Transform, &PlaytestOrigin)>,
+    hidden_q: Query<(Entity, &PlaytestHidden)>,
+) {
+    for ent in &spawned_q {
+        commands.entity(ent).despawn_recursive();
+    }
+    for (ent, mut transform, origin) in &mut origin_q {
+        *transform = origin.0;
+        commands
+            .entity(ent)
+            .remove::<(PlaytestOrigin, PatrolProgress)>();
+    }
+    for (ent, hidden) in &hidden_q {
+        commands
+            .entity(ent)
+            .insert(hidden.0)
+            .remove::<PlaytestHidden>();
+    }
+}
+
+fn overlaps(pos: Vec2, size: Vec2, collider: &Collider) -> bool {
+    // a hair of slack so resting exactly on an edge doesn't count
+    let half = (size + collider.size) / 2.0 - 0.01;
+    let delta = (pos - collider.pos.truncate()).abs();
+    delta.x < half.x && delta.y < half.y
+}
+
+pub fn move_body(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    time: Res<Time>,
+    properties: Res<TileProperties>,
+    tile_q: Query<(&Collider, &Tile)>,
+    mut body_q: Query<(&mut Transform, &mut PlaytestBody)>,
+) {
+    let Ok((mut transform, mut body)) = body_q.get_single_mut() else {
+        return;
+    };
+    // a long frame would let the body tunnel through a tile
+    let dt = time.delta_seconds().min(1.0 / 30.0);
+
+    let mut run = 0.0;
+    if keyboard_input.any_pressed([KeyCode::KeyA, KeyCode::ArrowLeft]) {
+        run -= 1.0;
+    }
+    if keyboard_input.any_pressed([KeyCode::KeyD, KeyCode::ArrowRight]) {
+        run += 1.0;
+    }
+    body.velocity.x = run * RUN_SPEED;
+    if body.grounded
+        && keyboard_input.any_just_pressed([KeyCode::Space, KeyCode::KeyW, KeyCode::ArrowUp])
+    {
+        body.velocity.y = JUMP_SPEED;
+    }
+    body.velocity.y = (body.velocity.y + GRAVITY * dt).max(-MAX_FALL_SPEED);
+
+    let half = BODY_SIZE / 2.0;
+    let mut pos = transform.translation.truncate();
+
+    // horizontal then vertical, so each overlap is pushed out along the axis it came from
+    pos.x += body.velocity.x * dt;
+    for (collider, tile) in &tile_q {
+        if properties.collision(tile.0) != CollisionType::Solid
+            || !overlaps(pos, BODY_SIZE, collider)
+        {
+            continue;
+        }
+        pos.x = match body.velocity.x > 0.0 {
+            true => collider.pos.x - collider.size.x / 2.0 - half.x,
+            false => collider.pos.x + collider.size.x / 2.0 + half.x,
+        };
+        body.velocity.x = 0.0;
+    }
+
+    let previous_bottom = pos.y - half.y;
+    pos.y += body.velocity.y * dt;
+    body.grounded = false;
+    for (collider, tile) in &tile_q {
+        if !overlaps(pos, BODY_SIZE, collider) {
+            continue;
+        }
+        let top = collider.pos.y + collider.size.y / 2.0;
+        let bottom = collider.pos.y - collider.size.y / 2.0;
+        match properties.collision(tile.0) {
+            CollisionType::Solid if body.velocity.y <= 0.0 => {
+                pos.y = top + half.y;
+                body.velocity.y = 0.0;
+                body.grounded = true;
+            }
+            CollisionType::Solid => {
+                pos.y = bottom - half.y;
+                body.velocity.y = 0.0;
+            }
+            // only lands from above, jumping up through it is fine
+            CollisionType::OneWay if body.velocity.y <= 0.0 && previous_bottom >= top => {
+                pos.y = top + half.y;
+                body.velocity.y = 0.0;
+                body.grounded = true;
+            }
+            CollisionType::SlopeUp | CollisionType::SlopeDown if body.velocity.y <= 0.0 => {
+                let left = collider.pos.x - collider.size.x / 2.0;
+                let along = ((pos.x - left) / collider.size.x).clamp(0.0, 1.0);
+                let rise = match properties.collision(tile.0) {
+                    CollisionType::SlopeUp => along,
+                    _ => 1.0 - along,
+                };
+                let ground = bottom + rise * collider.size.y;
+                if pos.y - half.y < ground {
+                    pos.y = ground + half.y;
+                    body.velocity.y = 0.0;
+                    body.grounded = true;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    transform.translation = pos.extend(transform.translation.z);
+}
+
+// hazards, kill zones and falling out of the level send the body back to the player spawn
+pub fn respawn_on_death(
+    player_q: Query<&Transform, (With<Player>, Without<PlaytestBody>)>,
+    hazard_q: Query<&Collider, With<Hazard>>,
+    region_q: Query<(&Collider, &Region)>,
+    properties: Res<TileProperties>,
+    tile_q: Query<(&Collider, &Tile)>,
+    mut body_q: Query<(&mut Transform, &mut PlaytestBody)>,
+) {
+    let (Ok(spawn), Ok((mut transform, mut body))) =
+        (player_q.get_single(), body_q.get_single_mut())
+    else {
+        return;
+    };
+    let pos = transform.translation.truncate();
+    // decoration without collision can hang below the floor
+    let lowest = tile_q
+        .iter()
+        .filter(|(_, tile)| properties.collision(tile.0) != CollisionType::None)
+        .map(|(collider, _)| collider.pos.y)
+        .reduce(f32::min)
+        .unwrap_or(spawn.translation.y);
+    let dead = hazard_q
+        .iter()
+        .any(|collider| overlaps(pos, BODY_SIZE, collider))
+        || region_q.iter().any(|(collider, region)| {
+            region.0 == RegionKind::KillZone && overlaps(pos, BODY_SIZE, collider)
+        })
+        || pos.y < lowest - FALL_LIMIT;
+    if dead {
+        transform.translation = spawn.translation.truncate().extend(transform.translation.z);
+        *body = PlaytestBody::default();
+    }
+}
+
+pub fn move_mobs(
+    time: Res<Time>,
+    mut mob_q: Query<(
+        &mut Transform,
+        &Mob,
+        &Patrol,
+        &PlaytestOrigin,
+        &mut PatrolProgress,
+    )>,
+) {
+    for (mut transform, mob, patrol, origin, mut progress) in &mut mob_q {
+        let route = patrol.route(origin.0.translation);
+        if route.len() < 2 {
+            continue;
+        }
+        let pos = transform.translation.truncate();
+        let target = route[progress.next.min(route.len() - 1)];
+        let step = mob.speed * 24.0 * time.delta_seconds();
+        if pos.distance(target) > step {
+            let pos = pos + (target - pos).normalize() * step;
+            transform.translation = pos.extend(transform.translation.z);
+            continue;
+        }
+        transform.translation = target.extend(transform.translation.z);
+        let last = route.len() - 1;
+        match patrol.mode {
+            PatrolMode::Loop => progress.next = (progress.next + 1) % route.len(),
+            PatrolMode::PingPong => {
+                if progress.next == last {
+                    progress.forward = false;
+                } else if progress.next == 0 {
+                    progress.forward = true;
+                }
+                progress.next = match progress.forward {
+                    true => progress.next + 1,
+                    false => progress.next - 1,
+                };
+            }
+        }
+    }
+}
+
+pub fn follow_body(
+    body_q: Query<&Transform, With<PlaytestBody>>,
+    mut camera_q: Query<&mut Transform, (With<Camera>, Without<PlaytestBody>)>,
+) {
+    let (Ok(body), Ok(mut camera)) = (body_q.get_single(), camera_q.get_single_mut()) else {
+        return;
+    };
+    camera.translation = body.translation.truncate().extend(camera.translation.z);
+}
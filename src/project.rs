@@ -0,0 +1,414 @@
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    dialog::{DialogAction, PendingAction},
+    level::{read_level, recovery_path, write_level, CurrentLevel, LevelData, OpenLevel},
+    level_properties::LevelBounds,
+    recovery::newer_recovery,
+    AppState, TextInput, BORDER_COLOR, BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR, PRESSED_BORDER,
+};
+
+const PROJECT_PATH: &str = "project.json";
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct ProjectLevel {
+    pub path: String,
+    // bottom-left cell of the level on the world map, unset until it is placed there
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<[i32; 2]>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+// leaving `from` through `edge` enters `to`
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Connection {
+    pub from: String,
+    pub to: String,
+    pub edge: Edge,
+}
+
+/// The levels and tilesets that make up a game, kept in `project.json` next to them.
+/// Levels join the project once they are saved, and the last one opened is reopened on
+/// the next start.
+#[derive(Resource, Serialize, Deserialize, Default)]
+pub struct Project {
+    #[serde(default)]
+    pub levels: Vec<ProjectLevel>,
+    #[serde(default)]
+    pub tilesets: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_opened: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub connections: Vec<Connection>,
+    // level whose delete button was clicked once and waits for a second click
+    #[serde(skip)]
+    pub confirm_delete: Option<usize>,
+}
+
+impl Project {
+    pub fn position(&self, path: &Path) -> Option<usize> {
+        self.levels
+            .iter()
+            .position(|level| Path::new(&level.path) == path)
+    }
+
+    pub fn level_path(&self, index: usize) -> Option<PathBuf> {
+        self.levels
+            .get(index)
+            .map(|level| PathBuf::from(&level.path))
+    }
+
+    pub fn write(&self) {
+        let result = serde_json::to_string_pretty(self)
+            .map_err(|err| err.to_string())
+            .and_then(|json| std::fs::write(PROJECT_PATH, json).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            error!("failed to write {PROJECT_PATH}: {err}");
+        }
+    }
+
+    // a level file name that is neither on disk nor in the project yet
+    fn unused_path(&self, stem: &str) -> PathBuf {
+        (1..)
+            .map(|n| PathBuf::from(format!("{stem}_{n}.json")))
+            .find(|path| !path.exists() && self.position(path).is_none())
+            .expect("some numbered file name is free")
+    }
+}
+
+#[derive(Component)]
+pub struct LevelBrowser;
+
+#[derive(Component, Clone, Copy)]
+pub enum BrowserButton {
+    Open(usize),
+    Duplicate(usize),
+    Delete(usize),
+    New,
+}
+
+// goes straight to the last opened level unless there is unsaved work to recover first
+pub fn load_project(
+    mut project: ResMut<Project>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut text_res: ResMut<TextInput>,
+    mut app_state: ResMut<NextState<AppState>>,
+    mut open_w: EventWriter<OpenLevel>,
+) {
+    let Ok(json) = std::fs::read_to_string(PROJECT_PATH) else {
+        info!("no project at {PROJECT_PATH}");
+        return;
+    };
+    match serde_json::from_str(&json) {
+        Ok(loaded) => *project = loaded,
+        Err(err) => {
+            warn!("failed to parse {PROJECT_PATH}: {err}");
+            return;
+        }
+    }
+
+    let Some(path) = project.last_opened.as_ref().map(PathBuf::from) else {
+        return;
+    };
+    current_level.path = path.clone();
+    if newer_recovery(&path).is_some() {
+        return;
+    }
+    match read_level(&path) {
+        Ok(level) if !level.tileset.is_empty() => {
+            text_res.0 = level.tileset;
+            app_state.set(AppState::InLevelEdit);
+            open_w.send(OpenLevel(path));
+        }
+        Ok(_) => {}
+        Err(err) => warn!("failed to reopen {}: {err}", path.display()),
+    }
+}
+
+// adds saved levels and their tilesets to the project and remembers the open level
+pub fn track_current_level(
+    current_level: Res<CurrentLevel>,
+    text_res: Res<TextInput>,
+    mut project: ResMut<Project>,
+) {
+    if !current_level.is_changed() || !current_level.path.exists() {
+        return;
+    }
+    let path = current_level.path.to_string_lossy().to_string();
+    let mut changed = false;
+    if project.position(&current_level.path).is_none() {
+        project.levels.push(ProjectLevel {
+            path: path.clone(),
+            position: None,
+        });
+        changed = true;
+    }
+    if project.last_opened.as_ref() != Some(&path) {
+        project.last_opened = Some(path);
+        changed = true;
+    }
+    if !text_res.0.is_empty() && !project.tilesets.contains(&text_res.0) {
+        project.tilesets.push(text_res.0.clone());
+        changed = true;
+    }
+    if changed {
+        project.write();
+    }
+}
+
+pub fn setup_level_browser(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                align_self: AlignSelf::Center,
+                justify_self: JustifySelf::Center,
+                min_width: Val::Px(360.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(12.0)),
+                border: UiRect::all(Val::Px(2.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR.with_alpha(0.9)),
+            border_color: BorderColor(BORDER_COLOR),
+            z_index: ZIndex::Global(5),
+            ..default()
+        })
+        .insert(LevelBrowser);
+}
+
+pub fn toggle_level_browser(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut project: ResMut<Project>,
+    mut browser_q: Query<&mut Style, With<LevelBrowser>>,
+) {
+    if !(keyboard_input.pressed(KeyCode::ControlLeft) && keyboard_input.just_pressed(KeyCode::KeyB))
+    {
+        return;
+    }
+    for mut style in &mut browser_q {
+        style.display = match style.display {
+            Display::None => Display::Flex,
+            _ => Display::None,
+        };
+    }
+    project.confirm_delete = None;
+}
+
+pub fn refresh_level_browser(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    project: Res<Project>,
+    current_level: Res<CurrentLevel>,
+    browser_q: Query<Entity, With<LevelBrowser>>,
+) {
+    if !(project.is_changed() || current_level.is_changed()) {
+        return;
+    }
+    let Ok(browser) = browser_q.get_single() else {
+        return;
+    };
+    commands.entity(browser).despawn_descendants();
+
+    let text_style = TextStyle {
+        font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+        font_size: 18.0,
+        color: HOVER_BORDER,
+    };
+    let button = ButtonBundle {
+        style: Style {
+            padding: UiRect::horizontal(Val::Px(4.0)),
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        border_color: BorderColor(BORDER_COLOR),
+        ..default()
+    };
+    let tilesets = match project.tilesets.is_empty() {
+        true => "-".to_string(),
+        false => project.tilesets.join(", "),
+    };
+
+    commands.entity(browser).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            "Levels",
+            TextStyle {
+                font_size: 22.0,
+                ..text_style.clone()
+            },
+        ));
+        parent.spawn(TextBundle::from_section(
+            format!("tilesets: {tilesets}"),
+            text_style.clone(),
+        ));
+        for (index, level) in project.levels.iter().enumerate() {
+            let open = Path::new(&level.path) == current_level.path;
+            let delete = match project.confirm_delete == Some(index) {
+                true => "confirm",
+                false => "delete",
+            };
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(4.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    let label = match open {
+                        true => format!("> {}", level.path),
+                        false => level.path.clone(),
+                    };
+                    for (label, action) in [
+                        (label, BrowserButton::Open(index)),
+                        ("duplicate".to_string(), BrowserButton::Duplicate(index)),
+                        (delete.to_string(), BrowserButton::Delete(index)),
+                    ] {
+                        row.spawn(button.clone())
+                            .with_children(|p| {
+                                p.spawn(TextBundle::from_section(label, text_style.clone()));
+                            })
+                            .insert(action);
+                    }
+                });
+        }
+        parent
+            .spawn(button.clone())
+            .with_children(|p| {
+                p.spawn(TextBundle::from_section("+ new level", text_style.clone()));
+            })
+            .insert(BrowserButton::New);
+    });
+}
+
+// opening asks about unsaved changes first, like closing and clearing do
+fn open_or_ask(
+    index: usize,
+    project: &Project,
+    current_level: &CurrentLevel,
+    pending: &mut PendingAction,
+    open_w: &mut EventWriter<OpenLevel>,
+) {
+    let Some(path) = project.level_path(index) else {
+        return;
+    };
+    if path == current_level.path {
+        return;
+    }
+    match current_level.dirty {
+        true => pending.0 = Some(DialogAction::Open(path)),
+        false => {
+            open_w.send(OpenLevel(path));
+        }
+    }
+}
+
+pub fn level_browser_interaction(
+    mut button_q: Query<(&Interaction, &mut BorderColor, &BrowserButton), Changed<Interaction>>,
+    mut project: ResMut<Project>,
+    current_level: Res<CurrentLevel>,
+    text_res: Res<TextInput>,
+    mut pending: ResMut<PendingAction>,
+    mut open_w: EventWriter<OpenLevel>,
+) {
+    for (interaction, mut border_color, action) in &mut button_q {
+        *border_color = match *interaction {
+            Interaction::Pressed => BorderColor(PRESSED_BORDER),
+            Interaction::Hovered => BorderColor(HOVER_BORDER),
+            Interaction::None => BorderColor(BORDER_COLOR),
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if !matches!(action, BrowserButton::Delete(_)) {
+            project.confirm_delete = None;
+        }
+        match *action {
+            BrowserButton::Open(index) => {
+                open_or_ask(index, &project, &current_level, &mut pending, &mut open_w);
+            }
+            BrowserButton::New => {
+                let path = project.unused_path("level");
+                let level = LevelData {
+                    tileset: text_res.0.clone(),
+                    bounds: Some(LevelBounds::default()),
+                    ..default()
+                };
+                if let Err(err) = write_level(&path, &level) {
+                    error!("failed to write {}: {err}", path.display());
+                    continue;
+                }
+                project.levels.push(ProjectLevel {
+                    path: path.to_string_lossy().to_string(),
+                    position: None,
+                });
+                project.write();
+                let index = project.levels.len() - 1;
+                open_or_ask(index, &project, &current_level, &mut pending, &mut open_w);
+            }
+            BrowserButton::Duplicate(index) => {
+                let Some(source) = project.level_path(index) else {
+                    continue;
+                };
+                let stem = source
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                let copy = project.unused_path(&format!("{stem}_copy"));
+                if let Err(err) = std::fs::copy(&source, &copy) {
+                    error!("failed to copy {}: {err}", source.display());
+                    continue;
+                }
+                project.levels.insert(
+                    index + 1,
+                    ProjectLevel {
+                        path: copy.to_string_lossy().to_string(),
+                        position: None,
+                    },
+                );
+                project.write();
+            }
+            BrowserButton::Delete(index) => {
+                let Some(path) = project.level_path(index) else {
+                    continue;
+                };
+                if path == current_level.path {
+                    warn!("can't delete the open level");
+                    continue;
+                }
+                if project.confirm_delete != Some(index) {
+                    project.confirm_delete = Some(index);
+                    continue;
+                }
+                for file in [path.clone(), recovery_path(&path)] {
+                    if let Err(err) = std::fs::remove_file(&file) {
+                        if err.kind() != std::io::ErrorKind::NotFound {
+                            error!("failed to remove {}: {err}", file.display());
+                        }
+                    }
+                }
+                let removed = project.levels.remove(index).path;
+                project
+                    .connections
+                    .retain(|connection| connection.from != removed && connection.to != removed);
+                project.confirm_delete = None;
+                project.write();
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::{path::Path, time::SystemTime};
+
+use bevy::prelude::*;
+
+use crate::{
+    entities::EntityRegistry,
+    level::{
+        read_level, recovery_path, spawn_level, write_level, CurrentLevel, LevelData, LevelQuery,
+    },
+    level_properties::LevelProperties,
+    links::NextEntityId,
+    mobs::MobRegistry,
+    tileset::Tileset,
+    AppState, TextInput, BORDER_COLOR, BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR, PRESSED_BORDER,
+};
+
+const AUTOSAVE_SECONDS: f32 = 60.0;
+
+#[derive(Resource)]
+pub struct AutosaveTimer(pub Timer);
+
+impl Default for AutosaveTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(AUTOSAVE_SECONDS, TimerMode::Repeating))
+    }
+}
+
+// recovered level waiting for the tileset to be set up in AppState::InLevelEdit
+#[derive(Resource)]
+pub struct PendingRestore(pub LevelData);
+
+#[derive(Component)]
+pub struct RecoveryPrompt;
+
+#[derive(Component, Clone, Copy)]
+pub enum RecoveryButton {
+    Restore,
+    Discard,
+}
+
+pub fn autosave(
+    time: Res<Time>,
+    mut timer: ResMut<AutosaveTimer>,
+    current_level: Res<CurrentLevel>,
+    text_res: Res<TextInput>,
+    level_q: LevelQuery,
+) {
+    if !timer.0.tick(time.delta()).just_finished() || !current_level.dirty {
+        return;
+    }
+
+    let path = recovery_path(&current_level.path);
+    match write_level(&path, &level_q.level_data(&text_res.0)) {
+        Ok(()) => info!("autosaved to {}", path.display()),
+        Err(err) => error!("autosave to {} failed: {err}", path.display()),
+    }
+}
+
+// the recovery file only matters if it was written after the last explicit save
+pub fn newer_recovery(path: &Path) -> Option<SystemTime> {
+    let recovered = std::fs::metadata(recovery_path(path))
+        .and_then(|meta| meta.modified())
+        .ok()?;
+    match std::fs::metadata(path).and_then(|meta| meta.modified()) {
+        Ok(saved) if saved >= recovered => None,
+        _ => Some(recovered),
+    }
+}
+
+pub fn setup_recovery_prompt(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    current_level: Res<CurrentLevel>,
+) {
+    let Some(recovered) = newer_recovery(&current_level.path) else {
+        return;
+    };
+    let minutes = recovered
+        .elapsed()
+        .map(|age| age.as_secs() / 60)
+        .unwrap_or_default();
+
+    let text_style = TextStyle {
+        font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+        font_size: 20.0,
+        color: HOVER_BORDER,
+    };
+    let button = ButtonBundle {
+        style: Style {
+            width: Val::Px(100.0),
+            height: Val::Px(36.0),
+            align_items: AlignItems::Center,
+            justify_content: JustifyContent::Center,
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        border_color: BorderColor(BORDER_COLOR),
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(16.0),
+                align_self: AlignSelf::Start,
+                justify_self: JustifySelf::Center,
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(8.0),
+                padding: UiRect::all(Val::Px(12.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(RecoveryPrompt)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!(
+                    "unsaved work from {minutes} min ago found in {}",
+                    recovery_path(&current_level.path).display()
+                ),
+                text_style.clone(),
+            ));
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    for (label, choice) in [
+                        ("Restore", RecoveryButton::Restore),
+                        ("Discard", RecoveryButton::Discard),
+                    ] {
+                        row.spawn(button.clone())
+                            .with_children(|p| {
+                                p.spawn(TextBundle::from_section(label, text_style.clone()));
+                            })
+                            .insert(choice);
+                    }
+                });
+        });
+}
+
+#[allow(clippy::type_complexity)]
+pub fn recovery_prompt_interaction(
+    mut commands: Commands,
+    mut interaction_q: Query<
+        (&Interaction, &mut BorderColor, &RecoveryButton),
+        (Changed<Interaction>, With<Button>),
+    >,
+    prompt_q: Query<Entity, With<RecoveryPrompt>>,
+    current_level: Res<CurrentLevel>,
+    mut text_res: ResMut<TextInput>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, mut color, choice) in &mut interaction_q {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = BorderColor(PRESSED_BORDER);
+                let path = recovery_path(&current_level.path);
+                match choice {
+                    RecoveryButton::Restore => match read_level(&path) {
+                        Ok(level) => {
+                            if !level.tileset.is_empty() {
+                                text_res.0 = level.tileset.clone();
+                            }
+                            commands.insert_resource(PendingRestore(level));
+                            app_state.set(AppState::InLevelEdit);
+                        }
+                        Err(err) => error!("failed to read {}: {err}", path.display()),
+                    },
+                    RecoveryButton::Discard => {
+                        if let Err(err) = std::fs::remove_file(&path) {
+                            error!("failed to remove {}: {err}", path.display());
+                        }
+                    }
+                }
+                for ent in &prompt_q {
+                    commands.entity(ent).despawn_recursive();
+                }
+            }
+            Interaction::Hovered => {
+                *color = BorderColor(HOVER_BORDER);
+            }
+            Interaction::None => {
+                *color = BorderColor(BORDER_COLOR);
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn restore_pending_level(
+    mut commands: Commands,
+    pending: Option<Res<PendingRestore>>,
+    tileset: Res<Tileset>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut level_properties: ResMut<LevelProperties>,
+    mut next_entity_id: ResMut<NextEntityId>,
+    mob_registry: Res<MobRegistry>,
+    entity_registry: Res<EntityRegistry>,
+    prompt_q: Query<Entity, With<RecoveryPrompt>>,
+) {
+    for ent in &prompt_q {
+        commands.entity(ent).despawn_recursive();
+    }
+    let Some(pending) = pending else {
+        return;
+    };
+
+    level_properties.bounds = pending.0.bounds();
+    level_properties.meta = pending.0.meta.clone();
+    next_entity_id.0 = pending.0.next_entity_id();
+    spawn_level(
+        &mut commands,
+        &pending.0,
+        &mob_registry,
+        &entity_registry,
+        tileset.texture.clone(),
+        tileset.layout.clone(),
+    );
+
+    // the restored content still has to be saved explicitly
+    current_level.dirty = true;
+    commands.remove_resource::<PendingRestore>();
+}
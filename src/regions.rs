@@ -0,0 +1,378 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    cursor_over_ui, idle_border, inspector::Selection, level::CurrentLevel,
+    level_properties::LevelProperties, ClickAnd, ClickState, Collider, ColliderBundle, LevelEntity,
+    BORDER_COLOR, BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR, PRESSED_BORDER,
+};
+
+const HANDLE_RADIUS: f32 = 6.0;
+// drags smaller than this are treated as a click rather than a new region
+const MIN_REGION_SIZE: f32 = 4.0;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionKind {
+    #[default]
+    Trigger,
+    KillZone,
+    Water,
+    CameraBounds,
+}
+
+impl RegionKind {
+    pub const ALL: [RegionKind; 4] = [
+        RegionKind::Trigger,
+        RegionKind::KillZone,
+        RegionKind::Water,
+        RegionKind::CameraBounds,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            RegionKind::Trigger => "trigger",
+            RegionKind::KillZone => "kill zone",
+            RegionKind::Water => "water",
+            RegionKind::CameraBounds => "camera bounds",
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            RegionKind::Trigger => Color::srgb(0.9, 0.8, 0.2),
+            RegionKind::KillZone => Color::srgb(0.9, 0.2, 0.2),
+            RegionKind::Water => Color::srgb(0.2, 0.5, 0.95),
+            RegionKind::CameraBounds => Color::srgb(0.7, 0.7, 0.7),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|kind| kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+// a free-sized rectangle, its bounds live in the Collider
+#[derive(Component, Clone, Copy)]
+pub struct Region(pub RegionKind);
+
+// kind the region tool draws
+#[derive(Resource, Default)]
+pub struct ActiveRegionKind(pub RegionKind);
+
+// region whose corner handle is being dragged
+#[derive(Resource, Default)]
+pub struct RegionResize(pub Option<Entity>);
+
+#[derive(Component)]
+pub struct RegionPalette;
+
+#[derive(Component)]
+pub struct RegionKindButton(pub RegionKind);
+
+pub fn region_bundle(
+    min: Vec2,
+    max: Vec2,
+    kind: RegionKind,
+) -> (SpriteBundle, LevelEntity, ColliderBundle<Region>) {
+    let center = ((min + max) / 2.0).extend(2.0);
+    let size = max - min;
+    (
+        SpriteBundle {
+            sprite: Sprite {
+                color: kind.color().with_alpha(0.25),
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_translation(center),
+            ..default()
+        },
+        LevelEntity,
+        ColliderBundle::new(center, size, Region(kind)),
+    )
+}
+
+fn corners(collider: &Collider) -> [Vec2; 4] {
+    let half = collider.size / 2.0;
+    let center = collider.pos.xy();
+    [
+        center + Vec2::new(-half.x, -half.y),
+        center + Vec2::new(half.x, -half.y),
+        center + Vec2::new(half.x, half.y),
+        center + Vec2::new(-half.x, half.y),
+    ]
+}
+
+fn contains(collider: &Collider, point: Vec2) -> bool {
+    (point - collider.pos.xy())
+        .abs()
+        .cmple(collider.size / 2.0)
+        .all()
+}
+
+// cursor position in world space, snapped to grid lines while shift is held
+fn cursor_world(
+    window_q: &Query<&Window, With<PrimaryWindow>>,
+    cam_q: &Query<(&Camera, &GlobalTransform)>,
+    keyboard_input: &ButtonInput<KeyCode>,
+) -> Option<Vec2> {
+    let cursor_pos = window_q.single().cursor_position()?;
+    let (camera, camera_transform) = cam_q.single();
+    let world_pos = camera.viewport_to_world_2d(camera_transform, cursor_pos)?;
+    Some(
+        match keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+            true => (world_pos / 24.0).round() * 24.0,
+            false => world_pos.round(),
+        },
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn edit_regions(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    state: Res<State<ClickAnd>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    node_q: Query<(&GlobalTransform, &Node)>,
+    mut region_q: Query<(Entity, &mut Collider), With<Region>>,
+    mut click_state: ResMut<ClickState>,
+    mut resize: ResMut<RegionResize>,
+    mut selection: ResMut<Selection>,
+    mut current_level: ResMut<CurrentLevel>,
+    level_properties: Res<LevelProperties>,
+) {
+    if *state.get() != ClickAnd::DrawRegion {
+        return;
+    }
+    let Some(cursor) = cursor_world(&window_q, &cam_q, &keyboard_input) else {
+        return;
+    };
+
+    if let ClickState::SecondClick(anchor) = *click_state {
+        let anchor = anchor.xy();
+        if let Some(Ok((_, mut collider))) = resize.0.map(|ent| region_q.get_mut(ent)) {
+            let size = (cursor - anchor).abs().max(Vec2::ONE);
+            let center = anchor + (cursor - anchor).signum() * size / 2.0;
+            if collider.size != size || collider.pos.xy() != center {
+                collider.size = size;
+                collider.pos = center.extend(collider.pos.z);
+            }
+        }
+        if mouse.just_released(MouseButton::Left) {
+            *click_state = match resize.0.take() {
+                Some(_) => {
+                    current_level.dirty = true;
+                    ClickState::FirstClick
+                }
+                None => ClickState::Draw([anchor.extend(0.0), cursor.extend(0.0)]),
+            };
+        }
+        return;
+    }
+
+    let over_ui = window_q
+        .single()
+        .cursor_position()
+        .is_some_and(|cursor_pos| cursor_over_ui(cursor_pos, &node_q));
+    if over_ui {
+        return;
+    }
+
+    if mouse.just_pressed(MouseButton::Right) {
+        if let Some((ent, _)) = region_q
+            .iter()
+            .find(|(_, collider)| contains(collider, cursor))
+        {
+            commands.entity(ent).despawn_recursive();
+            current_level.dirty = true;
+        }
+        return;
+    }
+    if !mouse.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    // dragging a handle of the selected region resizes it from the opposite corner
+    if let Some(Ok((ent, collider))) = selection.0.map(|ent| region_q.get(ent)) {
+        let corners = corners(collider);
+        if let Some(index) = corners
+            .iter()
+            .position(|corner| corner.distance(cursor) <= HANDLE_RADIUS)
+        {
+            resize.0 = Some(ent);
+            *click_state = ClickState::SecondClick(corners[(index + 2) % 4].extend(0.0));
+            return;
+        }
+    }
+    // the smallest region under the cursor wins so nested ones stay reachable
+    if let Some((ent, _)) = region_q
+        .iter()
+        .filter(|(_, collider)| contains(collider, cursor))
+        .min_by(|(_, a), (_, b)| (a.size.x * a.size.y).total_cmp(&(b.size.x * b.size.y)))
+    {
+        selection.0 = Some(ent);
+        return;
+    }
+    if !level_properties.bounds.contains_world(cursor) {
+        return;
+    }
+    *click_state = ClickState::SecondClick(cursor.extend(0.0));
+}
+
+pub fn spawn_drawn_region(
+    mut commands: Commands,
+    mut click_state: ResMut<ClickState>,
+    active_kind: Res<ActiveRegionKind>,
+    mut selection: ResMut<Selection>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    let ClickState::Draw([a, b]) = *click_state else {
+        return;
+    };
+    *click_state = ClickState::FirstClick;
+
+    let (min, max) = (a.xy().min(b.xy()), a.xy().max(b.xy()));
+    if (max - min).min_element() < MIN_REGION_SIZE {
+        return;
+    }
+    let ent = commands.spawn(region_bundle(min, max, active_kind.0)).id();
+    selection.0 = Some(ent);
+    current_level.dirty = true;
+}
+
+// keeps the sprite in line with collider and kind edits from the tool or the inspector
+#[allow(clippy::type_complexity)]
+pub fn sync_region_sprites(
+    mut region_q: Query<
+        (&Region, &Collider, &mut Transform, &mut Sprite),
+        Or<(Changed<Collider>, Changed<Region>)>,
+    >,
+) {
+    for (region, collider, mut transform, mut sprite) in &mut region_q {
+        transform.translation = collider.pos.xy().extend(transform.translation.z);
+        sprite.custom_size = Some(collider.size);
+        sprite.color = region.0.color().with_alpha(0.25);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn draw_regions(
+    mut gizmos: Gizmos,
+    state: Res<State<ClickAnd>>,
+    click_state: Res<ClickState>,
+    resize: Res<RegionResize>,
+    selection: Res<Selection>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    region_q: Query<(Entity, &Region, &Collider)>,
+) {
+    for (ent, region, collider) in &region_q {
+        gizmos.rect_2d(collider.pos.xy(), 0.0, collider.size, region.0.color());
+        if *state.get() == ClickAnd::DrawRegion && selection.0 == Some(ent) {
+            for corner in corners(collider) {
+                gizmos.circle_2d(corner, HANDLE_RADIUS, PRESSED_BORDER);
+            }
+        }
+    }
+
+    // outline of the region being drawn
+    let ClickState::SecondClick(anchor) = *click_state else {
+        return;
+    };
+    if resize.0.is_some() {
+        return;
+    }
+    if let Some(cursor) = cursor_world(&window_q, &cam_q, &keyboard_input) {
+        let anchor = anchor.xy();
+        gizmos.rect_2d(
+            (anchor + cursor) / 2.0,
+            0.0,
+            (cursor - anchor).abs(),
+            HOVER_BORDER,
+        );
+    }
+}
+
+pub fn setup_region_palette(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let button = ButtonBundle {
+        style: Style {
+            height: Val::Px(32.0),
+            padding: UiRect::horizontal(Val::Px(6.0)),
+            align_items: AlignItems::Center,
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: BackgroundColor(BUTTON_COLOR),
+        border_color: BorderColor(BORDER_COLOR),
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(88.0),
+                bottom: Val::Px(40.0),
+                column_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(4.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(RegionPalette)
+        .with_children(|parent| {
+            for kind in RegionKind::ALL {
+                let text_style = TextStyle {
+                    font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+                    font_size: 20.0,
+                    color: kind.color(),
+                };
+                parent
+                    .spawn(button.clone())
+                    .with_children(|p| {
+                        p.spawn(TextBundle::from_section(kind.label(), text_style));
+                    })
+                    .insert(RegionKindButton(kind));
+            }
+        });
+}
+
+pub fn toggle_region_palette(
+    state: Res<State<ClickAnd>>,
+    mut palette_q: Query<&mut Style, With<RegionPalette>>,
+) {
+    let display = match state.get() {
+        ClickAnd::DrawRegion => Display::Flex,
+        _ => Display::None,
+    };
+    for mut style in &mut palette_q {
+        if style.display != display {
+            style.display = display;
+        }
+    }
+}
+
+pub fn region_palette_interaction(
+    mut button_q: Query<(&Interaction, &mut BorderColor, &RegionKindButton)>,
+    mut active_kind: ResMut<ActiveRegionKind>,
+) {
+    for (interaction, mut color, kind_button) in &mut button_q {
+        if *interaction == Interaction::Pressed && active_kind.0 != kind_button.0 {
+            active_kind.0 = kind_button.0;
+        }
+        let border = match *interaction {
+            Interaction::Pressed => BorderColor(PRESSED_BORDER),
+            Interaction::Hovered => BorderColor(HOVER_BORDER),
+            Interaction::None => idle_border(kind_button.0 == active_kind.0),
+        };
+        if color.0 != border.0 {
+            *color = border;
+        }
+    }
+}
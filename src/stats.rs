@@ -0,0 +1,114 @@
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+use crate::{
+    entities::{display_value, FieldType},
+    level::{read_level, LevelData},
+    validation::ValidationRules,
+};
+
+// how many of each kind, for the per-kind lines
+fn count_kinds<'a>(kinds: impl Iterator<Item = &'a str>) -> String {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for kind in kinds {
+        *counts.entry(kind).or_default() += 1;
+    }
+    let total: usize = counts.values().sum();
+    match counts.is_empty() {
+        true => "0".to_string(),
+        false => {
+            let kinds: Vec<String> = counts
+                .iter()
+                .map(|(kind, count)| format!("{count} {kind}"))
+                .collect();
+            format!("{total} ({})", kinds.join(", "))
+        }
+    }
+}
+
+/// Summarises a level file for `--stats`: its metadata, what it contains and the problems
+/// the validation rules find, without opening a window.
+pub fn level_stats(level: &LevelData) -> String {
+    let mut out = String::new();
+    let meta = &level.meta;
+    let or_unset = |text: &str| match text.is_empty() {
+        true => "-".to_string(),
+        false => text.to_string(),
+    };
+    let bounds = level.bounds();
+
+    let _ = writeln!(out, "name: {}", or_unset(&meta.name));
+    let _ = writeln!(out, "author: {}", or_unset(&meta.author));
+    let _ = writeln!(out, "music: {}", or_unset(&meta.music));
+    let _ = writeln!(
+        out,
+        "par time: {}",
+        meta.par_time
+            .map(|time| format!("{time}s"))
+            .unwrap_or_else(|| "-".to_string())
+    );
+    for (name, value) in &meta.properties {
+        let _ = writeln!(
+            out,
+            "property {name} ({}): {}",
+            FieldType::of(value).label(),
+            display_value(value)
+        );
+    }
+    let _ = writeln!(out, "tileset: {}", or_unset(&level.tileset));
+    let _ = writeln!(
+        out,
+        "bounds: {}x{} cells from ({}, {})",
+        bounds.width, bounds.height, bounds.x, bounds.y
+    );
+    let _ = writeln!(
+        out,
+        "player: {}",
+        level
+            .player
+            .map(|cell| format!("({}, {})", cell.x, cell.y))
+            .unwrap_or_else(|| "-".to_string())
+    );
+    let _ = writeln!(out, "tiles: {}", level.tiles.len());
+    let _ = writeln!(
+        out,
+        "hazards: {}",
+        count_kinds(
+            level
+                .hazards
+                .iter()
+                .map(|hazard| hazard.kind.as_deref().unwrap_or("hazard"))
+        )
+    );
+    let _ = writeln!(
+        out,
+        "mobs: {}",
+        count_kinds(level.mobs.iter().map(|mob| mob.kind.as_str()))
+    );
+    let _ = writeln!(
+        out,
+        "entities: {}",
+        count_kinds(level.entities.iter().map(|entity| entity.kind.as_str()))
+    );
+    let _ = writeln!(
+        out,
+        "regions: {}",
+        count_kinds(level.regions.iter().map(|region| region.kind.label()))
+    );
+
+    let issues = ValidationRules::default().check(level);
+    let _ = writeln!(out, "problems: {}", issues.len());
+    for issue in issues {
+        let at = issue
+            .cell
+            .map(|cell| format!(" at ({}, {})", cell.x, cell.y))
+            .unwrap_or_default();
+        let _ = writeln!(out, "  {}: {}{at}", issue.rule, issue.message);
+    }
+    out
+}
+
+pub fn print_stats(path: &Path) -> Result<(), String> {
+    let level = read_level(path)?;
+    print!("{}", level_stats(&level));
+    Ok(())
+}
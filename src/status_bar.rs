@@ -0,0 +1,200 @@
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    autotile::TerrainBrushes,
+    entities::{CustomEntity, EntityRegistry},
+    hazards::HazardRegistry,
+    level::CurrentLevel,
+    links::PendingLink,
+    regions::{ActiveRegionKind, Region},
+    tileset::Tileset,
+    world_to_cell, ClickAnd, Hazard, Mob, PaintOrientation, Player, SelectedTile, Tile,
+    PANEL_COLOR,
+};
+
+#[derive(Component)]
+pub struct StatusText;
+
+#[derive(Component)]
+pub struct StatusTilePreview;
+
+#[derive(Component)]
+pub struct StatusInfo;
+
+pub fn setup_status_bar(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    tileset: Res<Tileset>,
+) {
+    let text_style = TextStyle {
+        font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+        font_size: 20.0,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(0.0),
+                left: Val::Px(80.0),
+                right: Val::Px(0.0),
+                height: Val::Px(32.0),
+                padding: UiRect::horizontal(Val::Px(8.0)),
+                column_gap: Val::Px(8.0),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ImageBundle {
+                        style: Style {
+                            width: Val::Px(24.0),
+                            height: Val::Px(24.0),
+                            ..default()
+                        },
+                        image: UiImage::new(tileset.texture.clone()),
+                        ..default()
+                    },
+                    TextureAtlas {
+                        index: 0,
+                        layout: tileset.layout.clone(),
+                    },
+                ))
+                .insert(StatusTilePreview);
+            parent
+                .spawn(TextBundle::from_section("", text_style.clone()))
+                .insert(StatusText);
+            parent
+                .spawn(TextBundle {
+                    style: Style {
+                        margin: UiRect::left(Val::Auto),
+                        ..default()
+                    },
+                    ..TextBundle::from_section("", text_style)
+                })
+                .insert(StatusInfo);
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_status_bar(
+    state: Res<State<ClickAnd>>,
+    selected_tile: Res<SelectedTile>,
+    paint_orientation: Res<PaintOrientation>,
+    terrain_brushes: Res<TerrainBrushes>,
+    hazard_registry: Res<HazardRegistry>,
+    entity_registry: Res<EntityRegistry>,
+    pending_link: Res<PendingLink>,
+    active_region: Res<ActiveRegionKind>,
+    mut text_q: Query<&mut Text, With<StatusText>>,
+    mut preview_q: Query<(&mut TextureAtlas, &mut UiImage, &mut Style), With<StatusTilePreview>>,
+) {
+    let orientation = paint_orientation.0;
+    let (selection, preview) = match state.get() {
+        ClickAnd::DrawTile | ClickAnd::DrawHazard => {
+            let mut selection = format!("tile {}", selected_tile.0);
+            if *state.get() == ClickAnd::DrawHazard {
+                selection = format!("{} {selection}", hazard_registry.selected().summary());
+            }
+            if orientation.flip_x {
+                selection.push_str(" flip-x");
+            }
+            if orientation.flip_y {
+                selection.push_str(" flip-y");
+            }
+            if orientation.rotation != 0 {
+                selection.push_str(&format!(" rot {}", orientation.rotation as u32 * 90));
+            }
+            (selection, Some(selected_tile.0))
+        }
+        ClickAnd::DrawEntity(index) => match entity_registry.types.get(*index) {
+            Some(entity_type) => (entity_type.name.clone(), None),
+            None => (String::new(), None),
+        },
+        ClickAnd::DrawRegion => (active_region.0.label().to_string(), None),
+        ClickAnd::Link => match &pending_link.0 {
+            Some(field) => (format!("click the {field} target"), None),
+            None => ("click an entity".to_string(), None),
+        },
+        ClickAnd::DrawTerrain => match terrain_brushes.active() {
+            Some(rule_set) => (rule_set.name.clone(), Some(rule_set.default_index)),
+            None => ("no terrain rules".to_string(), None),
+        },
+        _ => (String::new(), None),
+    };
+
+    for mut text in &mut text_q {
+        text.sections[0].value = format!("{}  {selection}", state.get().label());
+    }
+
+    for (mut atlas, mut image, mut style) in &mut preview_q {
+        match preview {
+            Some(index) => {
+                style.display = Display::Flex;
+                atlas.index = index;
+                let flips = !matches!(state.get(), ClickAnd::DrawTerrain);
+                image.flip_x = flips && orientation.flip_x;
+                image.flip_y = flips && orientation.flip_y;
+            }
+            None => style.display = Display::None,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn update_status_info(
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    current_level: Res<CurrentLevel>,
+    tile_q: Query<(), With<Tile>>,
+    hazard_q: Query<(), With<Hazard>>,
+    mob_q: Query<(), With<Mob>>,
+    entity_q: Query<(), With<CustomEntity>>,
+    region_q: Query<(), With<Region>>,
+    player_q: Query<(), With<Player>>,
+    mut text_q: Query<&mut Text, With<StatusInfo>>,
+) {
+    let (camera, camera_transform) = cam_q.single();
+    let cursor = window_q
+        .single()
+        .cursor_position()
+        .and_then(|cursor_pos| camera.viewport_to_world_2d(camera_transform, cursor_pos))
+        .map(|world_pos| {
+            let cell = world_to_cell(world_pos.extend(0.0));
+            format!(
+                "cell ({}, {})  world ({:.1}, {:.1})",
+                cell.x, cell.y, world_pos.x, world_pos.y
+            )
+        })
+        .unwrap_or_default();
+
+    let player = match player_q.is_empty() {
+        true => "no player",
+        false => "player",
+    };
+    let dirty = match current_level.dirty {
+        true => "*",
+        false => "",
+    };
+
+    for mut text in &mut text_q {
+        text.sections[0].value = format!(
+            "{cursor}  tiles {}  hazards {}  mobs {}  entities {}  regions {}  {player}  {}{dirty}",
+            tile_q.iter().count(),
+            hazard_q.iter().count(),
+            mob_q.iter().count(),
+            entity_q.iter().count(),
+            region_q.iter().count(),
+            current_level
+                .path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+        );
+    }
+}
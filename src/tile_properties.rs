@@ -0,0 +1,274 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    inspector::{spawn_fields, Field, FieldEdit, FieldKey},
+    SelectedTile, TextInput, Visible, PANEL_COLOR,
+};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum CollisionType {
+    #[default]
+    Solid,
+    OneWay,
+    // rising to the right
+    SlopeUp,
+    // rising to the left
+    SlopeDown,
+    None,
+}
+
+impl CollisionType {
+    const ALL: [CollisionType; 5] = [
+        CollisionType::Solid,
+        CollisionType::OneWay,
+        CollisionType::SlopeUp,
+        CollisionType::SlopeDown,
+        CollisionType::None,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            CollisionType::Solid => "solid",
+            CollisionType::OneWay => "one-way",
+            CollisionType::SlopeUp => "slope up",
+            CollisionType::SlopeDown => "slope down",
+            CollisionType::None => "none",
+        }
+    }
+
+    fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|kind| kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct TileProps {
+    #[serde(default)]
+    pub collision: CollisionType,
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+fn default_friction() -> f32 {
+    1.0
+}
+
+impl Default for TileProps {
+    fn default() -> Self {
+        Self {
+            collision: CollisionType::default(),
+            friction: default_friction(),
+            tags: Vec::new(),
+        }
+    }
+}
+
+/// Collision and gameplay properties of the tileset's atlas indices, read from and
+/// written back to `assets/<tileset>.tiles.json`. Indices without an entry are solid.
+#[derive(Resource, Default)]
+pub struct TileProperties {
+    // the tileset the table was read for, edits are only ever written back to its file
+    pub tileset: String,
+    pub tiles: BTreeMap<usize, TileProps>,
+    // edited since the table was last written
+    pub unsaved: bool,
+}
+
+impl TileProperties {
+    pub fn get(&self, index: usize) -> TileProps {
+        self.tiles.get(&index).cloned().unwrap_or_default()
+    }
+
+    pub fn apply(&mut self, index: usize, key: &FieldKey, value: &str) -> Result<(), String> {
+        let mut props = self.get(index);
+        apply_tile_property(&mut props, key, value)?;
+        self.tiles.insert(index, props);
+        self.unsaved = true;
+        Ok(())
+    }
+
+    pub fn collision(&self, index: usize) -> CollisionType {
+        self.tiles
+            .get(&index)
+            .map(|props| props.collision)
+            .unwrap_or_default()
+    }
+
+    // moves each entry to where its tile went in a resized tileset, entries for tiles
+    // that were cut off are dropped from the table
+    pub fn remap(&mut self, remap: impl Fn(usize) -> Option<usize>) {
+        for (index, props) in std::mem::take(&mut self.tiles) {
+            let to = remap(index);
+            if to != Some(index) {
+                self.unsaved = true;
+            }
+            match to {
+                Some(to) => {
+                    self.tiles.insert(to, props);
+                }
+                None => warn!("dropping the properties of tile {index}, it is outside the tileset"),
+            }
+        }
+    }
+
+    fn write(&self) {
+        let path = properties_path(&self.tileset);
+        let result = serde_json::to_string_pretty(&self.tiles)
+            .map_err(|err| err.to_string())
+            .and_then(|json| std::fs::write(&path, json).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            error!("failed to write {}: {err}", path.display());
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct TilePropertyPanel;
+
+fn properties_path(tileset: &str) -> PathBuf {
+    Path::new("assets")
+        .join(tileset)
+        .with_extension("tiles.json")
+}
+
+// (re)reads the table whenever a level switches to another tileset
+pub fn load_tile_properties(mut properties: ResMut<TileProperties>, text_res: Res<TextInput>) {
+    if properties.tileset == text_res.0 {
+        return;
+    }
+    // edits to the previous tileset still belong in its own file
+    if properties.unsaved {
+        properties.write();
+    }
+    *properties = TileProperties {
+        tileset: text_res.0.clone(),
+        ..default()
+    };
+
+    let path = properties_path(&text_res.0);
+    let Ok(json) = std::fs::read_to_string(&path) else {
+        info!("no tile properties at {}", path.display());
+        return;
+    };
+    match serde_json::from_str(&json) {
+        Ok(tiles) => properties.tiles = tiles,
+        Err(err) => warn!("failed to parse {}: {err}", path.display()),
+    }
+}
+
+pub fn save_tile_properties(mut properties: ResMut<TileProperties>) {
+    if !properties.unsaved {
+        return;
+    }
+    properties.unsaved = false;
+    properties.write();
+}
+
+pub fn tile_property_fields(props: &TileProps) -> Vec<Field> {
+    vec![
+        Field::editable(
+            FieldKey::TileCollision,
+            "collision",
+            props.collision.label(),
+        ),
+        Field::editable(FieldKey::TileFriction, "friction", props.friction),
+        Field::editable(FieldKey::TileTags, "tags", props.tags.join(", ")),
+    ]
+}
+
+fn apply_tile_property(props: &mut TileProps, key: &FieldKey, value: &str) -> Result<(), String> {
+    let value = value.trim();
+    match key {
+        FieldKey::TileCollision => props.collision = props.collision.next(),
+        FieldKey::TileFriction => {
+            props.friction = value
+                .parse()
+                .map_err(|_| format!("{value} is not a number"))?;
+        }
+        FieldKey::TileTags => {
+            props.tags = value
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(str::to_string)
+                .collect();
+        }
+        _ => return Err(format!("{key:?} is not a tile property")),
+    }
+    Ok(())
+}
+
+// sits to the right of the tile selector and edits the selected tile's entry
+pub fn setup_tile_property_panel(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Percent(50.0),
+                top: Val::Percent(50.0),
+                margin: UiRect {
+                    left: Val::Px(125.0),
+                    top: Val::Px(-100.0),
+                    ..default()
+                },
+                width: Val::Px(220.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(TilePropertyPanel);
+}
+
+pub fn refresh_tile_property_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    visible: Res<Visible>,
+    selected_tile: Res<SelectedTile>,
+    properties: Res<TileProperties>,
+    edit: Res<FieldEdit>,
+    mut panel_q: Query<(Entity, &mut Style), With<TilePropertyPanel>>,
+) {
+    if !(visible.is_changed()
+        || selected_tile.is_changed()
+        || properties.is_changed()
+        || edit.is_changed())
+    {
+        return;
+    }
+    let Ok((panel, mut style)) = panel_q.get_single_mut() else {
+        return;
+    };
+    let display = match visible.0 {
+        true => Display::Flex,
+        false => Display::None,
+    };
+    if style.display != display {
+        style.display = display;
+    }
+
+    commands.entity(panel).despawn_descendants();
+    let fields = tile_property_fields(&properties.get(selected_tile.0));
+    spawn_fields(
+        &mut commands,
+        panel,
+        &format!("Tile {}", selected_tile.0),
+        fields,
+        &edit,
+        &asset_server,
+    );
+}
@@ -0,0 +1,216 @@
+use bevy::prelude::*;
+
+use crate::{
+    autotile::TerrainBrushes, fit_tile_selector, level::CurrentLevel, spawn_tile_buttons,
+    tile_properties::TileProperties, Hazard, SelectedTile, TextInput, Tile, TileSelectionUi,
+    PRESSED_BORDER,
+};
+
+const TILE_SIZE: u32 = 24;
+
+/// Columns and rows of 24px tiles in the tileset image. Until the image has loaded this is
+/// the 4x4 grid the editor always assumed.
+#[derive(Resource)]
+pub struct TilesetGrid(pub UVec2);
+
+impl Default for TilesetGrid {
+    fn default() -> Self {
+        TilesetGrid(UVec2::splat(4))
+    }
+}
+
+impl TilesetGrid {
+    pub fn layout(&self) -> TextureAtlasLayout {
+        TextureAtlasLayout::from_grid(UVec2::splat(TILE_SIZE), self.0.x, self.0.y, None, None)
+    }
+
+    pub fn len(&self) -> usize {
+        (self.0.x * self.0.y) as usize
+    }
+
+    fn of(image: &Image) -> UVec2 {
+        (image.size() / TILE_SIZE).max(UVec2::ONE)
+    }
+
+    // where `index` ends up in `to` if its cell is still part of the image
+    fn remap(&self, index: usize, to: UVec2) -> Option<usize> {
+        let columns = self.0.x as usize;
+        let (column, row) = (index % columns, index / columns);
+        (column < to.x as usize && row < to.y as usize).then(|| row * to.x as usize + column)
+    }
+}
+
+/// The tileset image and the one atlas layout over it, created when editing starts and
+/// shared by the tile selector, painting and level loading.
+#[derive(Resource)]
+pub struct Tileset {
+    pub texture: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+}
+
+pub fn setup_tileset(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    text_res: Res<TextInput>,
+    grid: Res<TilesetGrid>,
+) {
+    commands.insert_resource(Tileset {
+        texture: asset_server.load(text_res.0.clone()),
+        layout: layouts.add(grid.layout()),
+    });
+}
+
+/// A tile or hazard whose index is past the end of the tileset after it was reloaded.
+#[derive(Component)]
+pub struct MissingTile;
+
+/// Resizes the tileset layout to the tileset image once it loads or changes size. The asset
+/// server's file watcher reloads the image when an artist saves it. On a reload, tiles and
+/// hazards keep their column and row, anything that no longer fits keeps its index and gets
+/// flagged by `flag_missing_tiles`. Tile properties, terrain rules and the selected tile
+/// move the same way.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_tileset_size(
+    mut image_r: EventReader<AssetEvent<Image>>,
+    tileset: Option<Res<Tileset>>,
+    images: Res<Assets<Image>>,
+    text_res: Res<TextInput>,
+    mut grid: ResMut<TilesetGrid>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut tile_q: Query<(&mut TextureAtlas, Option<&mut Tile>, Option<&mut Hazard>)>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut properties: ResMut<TileProperties>,
+    mut brushes: ResMut<TerrainBrushes>,
+    mut selected_tile: ResMut<SelectedTile>,
+) {
+    let mut changed: Vec<(AssetId<Image>, bool)> = image_r
+        .read()
+        .filter_map(|event| match event {
+            AssetEvent::LoadedWithDependencies { id } => Some((*id, false)),
+            AssetEvent::Modified { id } => Some((*id, true)),
+            _ => None,
+        })
+        .collect();
+    let Some(tileset) = tileset else {
+        return;
+    };
+    // switching back to a tileset that is already loaded sends no event
+    if tileset.is_changed() {
+        changed.push((tileset.texture.id(), false));
+    }
+    for (id, reloaded) in changed {
+        let Some(image) = images.get(id).filter(|_| id == tileset.texture.id()) else {
+            continue;
+        };
+        let size = TilesetGrid::of(image);
+        if size == grid.0 {
+            continue;
+        }
+        info!("tileset {} is now {}x{} tiles", text_res.0, size.x, size.y);
+
+        if reloaded {
+            for (mut atlas, tile, hazard) in &mut tile_q {
+                let index = match (tile, hazard) {
+                    (Some(tile), _) => &mut tile.into_inner().0,
+                    (_, Some(hazard)) => &mut hazard.into_inner().0,
+                    _ => continue,
+                };
+                let Some(remapped) = grid.remap(*index, size) else {
+                    continue;
+                };
+                if remapped != *index {
+                    *index = remapped;
+                    atlas.index = remapped;
+                    current_level.dirty = true;
+                }
+            }
+            // the tables keyed by index follow their tiles too
+            let remap = |index| grid.remap(index, size);
+            if properties.tileset == text_res.0 {
+                properties.remap(remap);
+            }
+            if brushes.tileset == text_res.0 {
+                brushes.remap(remap);
+            }
+            if let Some(remapped) = remap(selected_tile.0) {
+                selected_tile.0 = remapped;
+            }
+        }
+        grid.0 = size;
+        if let Some(layout) = layouts.get_mut(&tileset.layout) {
+            *layout = grid.layout();
+        }
+    }
+}
+
+// also picks up a different tileset image opened with a level
+pub fn rebuild_tile_selector(
+    mut commands: Commands,
+    tileset: Res<Tileset>,
+    grid: Res<TilesetGrid>,
+    mut selected_tile: ResMut<SelectedTile>,
+    mut selector_q: Query<(Entity, &mut Style), With<TileSelectionUi>>,
+) {
+    // the selector was just built from the current tileset
+    if tileset.is_added() || !(grid.is_changed() || tileset.is_changed()) {
+        return;
+    }
+    for (ent, mut style) in &mut selector_q {
+        fit_tile_selector(&mut style, grid.0);
+        commands
+            .entity(ent)
+            .despawn_descendants()
+            .with_children(|parent| {
+                spawn_tile_buttons(parent, &tileset.texture, &tileset.layout, grid.len());
+            });
+    }
+    if selected_tile.0 >= grid.len() {
+        selected_tile.0 = 0;
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn flag_missing_tiles(
+    mut commands: Commands,
+    grid: Res<TilesetGrid>,
+    tile_q: Query<
+        (Entity, Option<&Tile>, Option<&Hazard>, Has<MissingTile>),
+        Or<(Changed<Tile>, Changed<Hazard>)>,
+    >,
+    all_q: Query<(Entity, Option<&Tile>, Option<&Hazard>, Has<MissingTile>)>,
+) {
+    let mut flag =
+        |(ent, tile, hazard, flagged): (Entity, Option<&Tile>, Option<&Hazard>, bool)| {
+            let Some(index) = tile.map(|tile| tile.0).or(hazard.map(|hazard| hazard.0)) else {
+                return;
+            };
+            match (index >= grid.len(), flagged) {
+                (true, false) => {
+                    warn!("tile index {index} is outside the tileset");
+                    commands.entity(ent).insert(MissingTile);
+                }
+                (false, true) => {
+                    commands.entity(ent).remove::<MissingTile>();
+                }
+                _ => {}
+            }
+        };
+    match grid.is_changed() {
+        true => all_q.iter().for_each(&mut flag),
+        false => tile_q.iter().for_each(&mut flag),
+    }
+}
+
+pub fn draw_missing_tiles(mut gizmos: Gizmos, missing_q: Query<&Transform, With<MissingTile>>) {
+    for transform in &missing_q {
+        let center = transform.translation.truncate();
+        let corner = Vec2::splat(10.0);
+        gizmos.line_2d(center - corner, center + corner, PRESSED_BORDER);
+        gizmos.line_2d(
+            center + Vec2::new(-corner.x, corner.y),
+            center + Vec2::new(corner.x, -corner.y),
+            PRESSED_BORDER,
+        );
+    }
+}
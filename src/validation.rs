@@ -0,0 +1,519 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::{
+    cell_to_world,
+    level::{CellData, LevelData, LevelQuery},
+    tile_properties::CollisionType,
+    TextInput, BORDER_COLOR, BUTTON_COLOR, HOVER_BORDER, PANEL_COLOR, PRESSED_BORDER,
+};
+
+// something wrong with the level, `cell` is where the camera pans to when it's clicked
+pub struct Issue {
+    pub rule: &'static str,
+    pub message: String,
+    pub cell: Option<IVec2>,
+}
+
+impl Issue {
+    fn at(rule: &'static str, cell: CellData, message: String) -> Self {
+        Self {
+            rule,
+            message,
+            cell: Some(IVec2::new(cell.x, cell.y)),
+        }
+    }
+}
+
+pub type Rule = fn(&LevelData) -> Vec<Issue>;
+
+/// The checks run over a level on save and on CTRL-v. Each rule looks at the saved form
+/// of the level, so anything that can be written can be checked; push to `rules` to add
+/// one.
+#[derive(Resource)]
+pub struct ValidationRules {
+    pub rules: Vec<Rule>,
+}
+
+impl Default for ValidationRules {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                missing_player,
+                overlapping_entities,
+                outside_bounds,
+                unreachable_exits,
+                duplicate_cells,
+            ],
+        }
+    }
+}
+
+impl ValidationRules {
+    pub fn check(&self, level: &LevelData) -> Vec<Issue> {
+        self.rules.iter().flat_map(|rule| rule(level)).collect()
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+    pub shown: bool,
+}
+
+// asks for a validation pass, a clean result only opens the panel when asked for by hand
+#[derive(Event, Clone, Copy)]
+pub struct ValidateLevel {
+    pub show_clean: bool,
+}
+
+#[derive(Component)]
+pub struct ValidationPanel;
+
+#[derive(Component)]
+pub struct IssueButton(IVec2);
+
+fn cell_of(cell: CellData) -> IVec2 {
+    IVec2::new(cell.x, cell.y)
+}
+
+// cells a body can't pass through, one-way platforms and slopes don't count
+fn solid_cells(level: &LevelData) -> HashSet<IVec2> {
+    level
+        .tiles
+        .iter()
+        .filter(|tile| {
+            level
+                .tile_properties
+                .get(&tile.index)
+                .map_or(CollisionType::Solid, |props| props.collision)
+                == CollisionType::Solid
+        })
+        .map(|tile| cell_of(tile.cell))
+        .collect()
+}
+
+fn is_exit(kind: &str) -> bool {
+    kind.eq_ignore_ascii_case("exit")
+}
+
+fn missing_player(level: &LevelData) -> Vec<Issue> {
+    match level.player {
+        Some(_) => Vec::new(),
+        None => vec![Issue {
+            rule: "missing player",
+            message: "the level has no player spawn".to_string(),
+            cell: None,
+        }],
+    }
+}
+
+fn overlapping_entities(level: &LevelData) -> Vec<Issue> {
+    let solid = solid_cells(level);
+    let hazards: HashSet<IVec2> = level
+        .hazards
+        .iter()
+        .map(|hazard| cell_of(hazard.cell))
+        .collect();
+    let rule = "overlapping entities";
+    let mut issues = Vec::new();
+    if let Some(player) = level.player {
+        if solid.contains(&cell_of(player)) {
+            issues.push(Issue::at(
+                rule,
+                player,
+                "player spawns inside a solid tile".into(),
+            ));
+        }
+        if hazards.contains(&cell_of(player)) {
+            issues.push(Issue::at(rule, player, "hazard on the player spawn".into()));
+        }
+    }
+    for mob in &level.mobs {
+        if solid.contains(&cell_of(mob.cell)) {
+            issues.push(Issue::at(
+                rule,
+                mob.cell,
+                format!("{} is inside a solid tile", mob.kind),
+            ));
+        }
+    }
+    for entity in &level.entities {
+        if solid.contains(&cell_of(entity.cell)) {
+            issues.push(Issue::at(
+                rule,
+                entity.cell,
+                format!("{} is inside a solid tile", entity.kind),
+            ));
+        }
+    }
+    for tile in &level.tiles {
+        if hazards.contains(&cell_of(tile.cell)) {
+            issues.push(Issue::at(
+                rule,
+                tile.cell,
+                "hazard and tile share a cell".into(),
+            ));
+        }
+    }
+    issues
+}
+
+fn outside_bounds(level: &LevelData) -> Vec<Issue> {
+    let bounds = level.bounds();
+    let inside = |cell: CellData| bounds.contains(cell_of(cell));
+
+    let placed = level
+        .player
+        .iter()
+        .map(|cell| (*cell, "player".to_string()))
+        .chain(
+            level
+                .tiles
+                .iter()
+                .map(|tile| (tile.cell, "tile".to_string())),
+        )
+        .chain(
+            level
+                .hazards
+                .iter()
+                .map(|hazard| (hazard.cell, "hazard".to_string())),
+        )
+        .chain(level.mobs.iter().map(|mob| (mob.cell, mob.kind.clone())))
+        .chain(
+            level
+                .entities
+                .iter()
+                .map(|entity| (entity.cell, entity.kind.clone())),
+        );
+    placed
+        .filter(|(cell, _)| !inside(*cell))
+        .map(|(cell, name)| Issue::at("outside bounds", cell, format!("{name} is out of bounds")))
+        .collect()
+}
+
+/// Flags exit entities that are walled off from the player spawn. Reachability is a flood
+/// fill through non-solid cells, it ignores jump height, so it only catches exits that
+/// can't be reached at all.
+fn unreachable_exits(level: &LevelData) -> Vec<Issue> {
+    let Some(player) = level.player else {
+        return Vec::new();
+    };
+    let exits: Vec<CellData> = level
+        .entities
+        .iter()
+        .filter(|entity| is_exit(&entity.kind))
+        .map(|entity| entity.cell)
+        .collect();
+    if exits.is_empty() {
+        return Vec::new();
+    }
+
+    let solid = solid_cells(level);
+    // one free ring around everything placed, so open levels connect around the outside
+    let cells = solid
+        .iter()
+        .copied()
+        .chain(std::iter::once(cell_of(player)))
+        .chain(exits.iter().map(|cell| cell_of(*cell)));
+    let (min, max) = cells.fold((cell_of(player), cell_of(player)), |(min, max), cell| {
+        (min.min(cell), max.max(cell))
+    });
+    let (min, max) = (min - IVec2::ONE, max + IVec2::ONE);
+
+    let mut reached = HashSet::from([cell_of(player)]);
+    let mut queue = VecDeque::from([cell_of(player)]);
+    while let Some(cell) = queue.pop_front() {
+        for step in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+            let next = cell + step;
+            if next.cmplt(min).any() || next.cmpgt(max).any() || solid.contains(&next) {
+                continue;
+            }
+            if reached.insert(next) {
+                queue.push_back(next);
+            }
+        }
+    }
+
+    exits
+        .into_iter()
+        .filter(|cell| !reached.contains(&cell_of(*cell)))
+        .map(|cell| {
+            Issue::at(
+                "unreachable exit",
+                cell,
+                "exit is walled off from the player spawn".into(),
+            )
+        })
+        .collect()
+}
+
+fn duplicate_cells(level: &LevelData) -> Vec<Issue> {
+    let layers: [(&str, Vec<CellData>); 4] = [
+        ("tiles", level.tiles.iter().map(|tile| tile.cell).collect()),
+        (
+            "hazards",
+            level.hazards.iter().map(|hazard| hazard.cell).collect(),
+        ),
+        ("mobs", level.mobs.iter().map(|mob| mob.cell).collect()),
+        (
+            "entities",
+            level.entities.iter().map(|entity| entity.cell).collect(),
+        ),
+    ];
+    let mut issues = Vec::new();
+    for (name, cells) in layers {
+        let mut counts: HashMap<IVec2, usize> = HashMap::new();
+        for cell in &cells {
+            *counts.entry(cell_of(*cell)).or_default() += 1;
+        }
+        let mut stacked: Vec<(IVec2, usize)> =
+            counts.into_iter().filter(|(_, count)| *count > 1).collect();
+        stacked.sort_by_key(|(cell, _)| (cell.y, cell.x));
+        issues.extend(stacked.into_iter().map(|(cell, count)| Issue {
+            rule: "duplicate cells",
+            message: format!("{count} {name} stacked in one cell"),
+            cell: Some(cell),
+        }));
+    }
+    issues
+}
+
+pub fn request_validation(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut report: ResMut<ValidationReport>,
+    mut validate_w: EventWriter<ValidateLevel>,
+) {
+    if !(keyboard_input.pressed(KeyCode::ControlLeft) && keyboard_input.just_pressed(KeyCode::KeyV))
+    {
+        return;
+    }
+    match report.shown {
+        true => report.shown = false,
+        false => {
+            validate_w.send(ValidateLevel { show_clean: true });
+        }
+    }
+}
+
+pub fn validate_level(
+    mut validate_r: EventReader<ValidateLevel>,
+    text_res: Res<TextInput>,
+    rules: Res<ValidationRules>,
+    level_q: LevelQuery,
+    mut report: ResMut<ValidationReport>,
+) {
+    let Some(show_clean) = validate_r
+        .read()
+        .map(|event| event.show_clean)
+        .reduce(|a, b| a || b)
+    else {
+        return;
+    };
+    let issues = rules.check(&level_q.level_data(&text_res.0));
+    for issue in &issues {
+        warn!("{}: {}", issue.rule, issue.message);
+    }
+    report.shown = show_clean || !issues.is_empty();
+    report.issues = issues;
+}
+
+pub fn setup_validation_panel(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(88.0),
+                top: Val::Px(100.0),
+                width: Val::Px(320.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(4.0),
+                padding: UiRect::all(Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: BackgroundColor(PANEL_COLOR),
+            ..default()
+        })
+        .insert(ValidationPanel);
+}
+
+pub fn refresh_validation_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    report: Res<ValidationReport>,
+    mut panel_q: Query<(Entity, &mut Style), With<ValidationPanel>>,
+) {
+    if !report.is_changed() {
+        return;
+    }
+    let Ok((panel, mut style)) = panel_q.get_single_mut() else {
+        return;
+    };
+    style.display = match report.shown {
+        true => Display::Flex,
+        false => Display::None,
+    };
+    commands.entity(panel).despawn_descendants();
+    if !report.shown {
+        return;
+    }
+
+    let text_style = TextStyle {
+        font: asset_server.load("../assets/FiraSans-Bold.ttf"),
+        font_size: 18.0,
+        color: HOVER_BORDER,
+    };
+    let title = match report.issues.len() {
+        0 => "no problems found".to_string(),
+        1 => "1 problem".to_string(),
+        count => format!("{count} problems"),
+    };
+    commands.entity(panel).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            title,
+            TextStyle {
+                font_size: 22.0,
+                ..text_style.clone()
+            },
+        ));
+        for issue in &report.issues {
+            let text = format!("{}: {}", issue.rule, issue.message);
+            let Some(cell) = issue.cell else {
+                parent.spawn(TextBundle::from_section(text, text_style.clone()));
+                continue;
+            };
+            parent
+                .spawn(ButtonBundle {
+                    style: Style {
+                        padding: UiRect::horizontal(Val::Px(4.0)),
+                        border: UiRect::all(Val::Px(2.0)),
+                        ..default()
+                    },
+                    background_color: BackgroundColor(BUTTON_COLOR),
+                    border_color: BorderColor(BORDER_COLOR),
+                    ..default()
+                })
+                .with_children(|p| {
+                    p.spawn(TextBundle::from_section(text, text_style.clone()));
+                })
+                .insert(IssueButton(cell));
+        }
+    });
+}
+
+// clicking a problem centers the camera on its cell
+pub fn issue_button_interaction(
+    mut button_q: Query<(&Interaction, &mut BorderColor, &IssueButton), Changed<Interaction>>,
+    mut camera_q: Query<&mut Transform, With<Camera>>,
+) {
+    for (interaction, mut border_color, IssueButton(cell)) in &mut button_q {
+        *border_color = match *interaction {
+            Interaction::Pressed => BorderColor(PRESSED_BORDER),
+            Interaction::Hovered => BorderColor(HOVER_BORDER),
+            Interaction::None => BorderColor(BORDER_COLOR),
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Ok(mut camera) = camera_q.get_single_mut() {
+            camera.translation = cell_to_world(*cell).truncate().extend(camera.translation.z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    // a level in its saved form, the required lists default to empty
+    fn level(mut saved: serde_json::Value) -> LevelData {
+        let fields = saved.as_object_mut().unwrap();
+        for key in ["tiles", "hazards", "mobs"] {
+            fields.entry(key).or_insert(json!([]));
+        }
+        fields.entry("tileset").or_insert(json!("tiles.png"));
+        fields.entry("player").or_insert(json!(null));
+        serde_json::from_value(saved).unwrap()
+    }
+
+    fn messages(issues: Vec<Issue>) -> Vec<String> {
+        issues.into_iter().map(|issue| issue.message).collect()
+    }
+
+    #[test]
+    fn missing_player_needs_a_spawn() {
+        assert_eq!(missing_player(&level(json!({}))).len(), 1);
+        let spawned = level(json!({ "player": { "x": 0, "y": 0 } }));
+        assert!(missing_player(&spawned).is_empty());
+    }
+
+    #[test]
+    fn overlapping_entities_skips_tiles_without_collision() {
+        let mut overlapping = level(json!({
+            "player": { "x": 0, "y": 0 },
+            "tiles": [{ "x": 0, "y": 0, "index": 3 }],
+            "mobs": [{ "x": 0, "y": 0, "kind": "slime" }],
+        }));
+        assert_eq!(
+            messages(overlapping_entities(&overlapping)),
+            [
+                "player spawns inside a solid tile",
+                "slime is inside a solid tile"
+            ]
+        );
+
+        overlapping.tile_properties = serde_json::from_value(json!({
+            "3": { "collision": "none" },
+        }))
+        .unwrap();
+        assert!(overlapping_entities(&overlapping).is_empty());
+    }
+
+    #[test]
+    fn outside_bounds_names_what_is_out() {
+        let level = level(json!({
+            "bounds": { "x": 0, "y": 0, "width": 4, "height": 4 },
+            "player": { "x": 1, "y": 1 },
+            "hazards": [{ "x": 4, "y": 0, "index": 0 }],
+        }));
+        assert_eq!(
+            messages(outside_bounds(&level)),
+            ["hazard is out of bounds"]
+        );
+    }
+
+    #[test]
+    fn unreachable_exits_finds_walled_off_exits() {
+        let wall =
+            [(3, 0), (5, 0), (4, 1), (4, -1)].map(|(x, y)| json!({ "x": x, "y": y, "index": 0 }));
+        let mut walled = level(json!({
+            "player": { "x": 0, "y": 0 },
+            "tiles": wall,
+            "entities": [{ "x": 4, "y": 0, "kind": "Exit" }],
+        }));
+        assert_eq!(unreachable_exits(&walled).len(), 1);
+
+        // one gap in the wall is enough
+        walled.tiles.pop();
+        assert!(unreachable_exits(&walled).is_empty());
+    }
+
+    #[test]
+    fn duplicate_cells_counts_each_layer_on_its_own() {
+        let level = level(json!({
+            "tiles": [
+                { "x": 1, "y": 2, "index": 0 },
+                { "x": 1, "y": 2, "index": 5 },
+            ],
+            "hazards": [{ "x": 1, "y": 2, "index": 0 }],
+        }));
+        let issues = duplicate_cells(&level);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].message, "2 tiles stacked in one cell");
+        assert_eq!(issues[0].cell, Some(IVec2::new(1, 2)));
+    }
+}
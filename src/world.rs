@@ -0,0 +1,399 @@
+use std::path::Path;
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::{
+    level::{read_level, CurrentLevel},
+    level_properties::BoundsBackground,
+    project::{Connection, Edge, Project},
+    AppState, LevelEntity, HOVER_BORDER, PRESSED_BORDER,
+};
+
+const CELL: f32 = 24.0;
+// camera zoom while the world map is shown
+const WORLD_SCALE: f32 = 3.0;
+// gap between levels that have no place on the map yet
+const UNPLACED_GAP: i32 = 8;
+const TILE_COLOR: Color = Color::srgba(0.9, 0.9, 0.9, 0.6);
+const CONNECTION_COLOR: Color = Color::srgb(0.443, 0.941, 0.353);
+
+/// One project level laid out on the world map, sized to its bounds. `cells` are its tiles
+/// relative to the bottom-left corner, drawn as the thumbnail.
+#[derive(Component)]
+pub struct LevelThumbnail {
+    index: usize,
+    size: IVec2,
+    cells: Vec<IVec2>,
+}
+
+// editor UI and the open level are hidden on the world map, this keeps what they were
+#[derive(Component)]
+pub struct WorldHidden(Visibility);
+
+// where the camera was before the world map zoomed out
+#[derive(Resource)]
+pub struct WorldCamera {
+    transform: Transform,
+    scale: f32,
+}
+
+#[derive(Clone, Copy)]
+pub enum WorldDrag {
+    // moving a level, `grab` is where it was picked up relative to its corner
+    Move { entity: Entity, grab: Vec2 },
+    // drawing a connection out of a level
+    Connect { entity: Entity },
+}
+
+#[derive(Resource, Default)]
+pub struct WorldDragState(pub Option<WorldDrag>);
+
+pub fn start_world_view(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.pressed(KeyCode::ControlLeft) && keyboard_input.just_pressed(KeyCode::KeyM) {
+        app_state.set(AppState::WorldView);
+    }
+}
+
+pub fn stop_world_view(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut app_state: ResMut<NextState<AppState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        app_state.set(AppState::InLevelEdit);
+    }
+}
+
+fn corner(thumbnail: &Transform) -> IVec2 {
+    (thumbnail.translation.truncate() / CELL).round().as_ivec2()
+}
+
+#[allow(clippy::type_complexity)]
+pub fn enter_world_view(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    project: Res<Project>,
+    current_level: Res<CurrentLevel>,
+    mut camera_q: Query<(&mut Transform, &mut OrthographicProjection)>,
+    hidden_q: Query<
+        (Entity, &Visibility),
+        Or<(
+            (With<Node>, Without<Parent>),
+            With<LevelEntity>,
+            With<BoundsBackground>,
+        )>,
+    >,
+) {
+    for (ent, visibility) in &hidden_q {
+        commands
+            .entity(ent)
+            .insert((WorldHidden(*visibility), Visibility::Hidden));
+    }
+
+    let font = asset_server.load("../assets/FiraSans-Bold.ttf");
+    let levels: Vec<_> = project
+        .levels
+        .iter()
+        .enumerate()
+        .filter_map(|(index, level)| match read_level(Path::new(&level.path)) {
+            Ok(data) => Some((index, level, data)),
+            Err(err) => {
+                warn!("failed to read {}: {err}", level.path);
+                None
+            }
+        })
+        .collect();
+    // levels not placed yet line up to the right of the placed ones
+    let mut next_free = levels
+        .iter()
+        .filter_map(|(_, level, data)| Some(level.position?[0] + data.bounds().width))
+        .max()
+        .map_or(0, |right| right + UNPLACED_GAP);
+    let mut focus = Vec2::ZERO;
+    for (index, level, data) in levels {
+        let bounds = data.bounds();
+        let size = IVec2::new(bounds.width, bounds.height);
+        let position = match level.position {
+            Some([x, y]) => IVec2::new(x, y),
+            None => {
+                let position = IVec2::new(next_free, 0);
+                next_free += size.x + UNPLACED_GAP;
+                position
+            }
+        };
+        let cells = data
+            .tiles
+            .iter()
+            .map(|tile| IVec2::new(tile.cell.x, tile.cell.y) - bounds.rect().min)
+            .collect();
+        let name = match data.meta.name.is_empty() {
+            true => level.path.clone(),
+            false => data.meta.name.clone(),
+        };
+        let world_size = size.as_vec2() * CELL;
+        if Path::new(&level.path) == current_level.path {
+            focus = position.as_vec2() * CELL + world_size / 2.0;
+        }
+
+        commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: bounds.background_color(),
+                        custom_size: Some(world_size),
+                        anchor: bevy::sprite::Anchor::BottomLeft,
+                        ..default()
+                    },
+                    transform: Transform::from_translation((position.as_vec2() * CELL).extend(0.0)),
+                    ..default()
+                },
+                LevelThumbnail { index, size, cells },
+            ))
+            .with_children(|parent| {
+                parent.spawn(Text2dBundle {
+                    text: Text::from_section(
+                        name,
+                        TextStyle {
+                            font: font.clone(),
+                            font_size: 20.0 * WORLD_SCALE,
+                            color: HOVER_BORDER,
+                        },
+                    ),
+                    text_anchor: bevy::sprite::Anchor::BottomLeft,
+                    transform: Transform::from_translation(Vec3::new(0.0, world_size.y, 1.0)),
+                    ..default()
+                });
+            });
+    }
+
+    if let Ok((mut transform, mut projection)) = camera_q.get_single_mut() {
+        commands.insert_resource(WorldCamera {
+            transform: *transform,
+            scale: projection.scale,
+        });
+        transform.translation = focus.extend(transform.translation.z);
+        projection.scale = WORLD_SCALE;
+    }
+}
+
+// puts the editor back the way it was left
+pub fn exit_world_view(
+    mut commands: Commands,
+    world_camera: Option<Res<WorldCamera>>,
+    mut drag: ResMut<WorldDragState>,
+    thumbnail_q: Query<Entity, With<LevelThumbnail>>,
+    hidden_q: Query<(Entity, &WorldHidden)>,
+    mut camera_q: Query<(&mut Transform, &mut OrthographicProjection)>,
+) {
+    for ent in &thumbnail_q {
+        commands.entity(ent).despawn_recursive();
+    }
+    for (ent, hidden) in &hidden_q {
+        commands
+            .entity(ent)
+            .insert(hidden.0)
+            .remove::<WorldHidden>();
+    }
+    if let (Some(world_camera), Ok((mut transform, mut projection))) =
+        (world_camera, camera_q.get_single_mut())
+    {
+        *transform = world_camera.transform;
+        projection.scale = world_camera.scale;
+        commands.remove_resource::<WorldCamera>();
+    }
+    drag.0 = None;
+}
+
+// the side of `from` that faces `to`
+fn facing_edge(from: Rect, to: Rect) -> Edge {
+    let delta = to.center() - from.center();
+    // compare in units of the combined extents so long thin levels pick the right side
+    let extent = (from.size() + to.size()) / 2.0;
+    match (delta.x / extent.x).abs() > (delta.y / extent.y).abs() {
+        true if delta.x > 0.0 => Edge::Right,
+        true => Edge::Left,
+        false if delta.y > 0.0 => Edge::Top,
+        false => Edge::Bottom,
+    }
+}
+
+fn thumbnail_rect(transform: &Transform, thumbnail: &LevelThumbnail) -> Rect {
+    let min = transform.translation.truncate();
+    Rect::from_corners(min, min + thumbnail.size.as_vec2() * CELL)
+}
+
+/// Left drag moves a level and snaps it to the cell grid, right drag from one level to
+/// another adds a connection through the facing edge, or removes it if it exists.
+pub fn drag_levels(
+    mouse: Res<ButtonInput<MouseButton>>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    mut thumbnail_q: Query<(Entity, &mut Transform, &LevelThumbnail)>,
+    mut drag: ResMut<WorldDragState>,
+    mut project: ResMut<Project>,
+) {
+    let Some(cursor) = window_q.single().cursor_position() else {
+        return;
+    };
+    let (camera, camera_transform) = cam_q.single();
+    let Some(cursor) = camera.viewport_to_world_2d(camera_transform, cursor) else {
+        return;
+    };
+
+    match drag.0 {
+        None => {
+            let picked = thumbnail_q
+                .iter()
+                .filter(|(_, transform, thumbnail)| {
+                    thumbnail_rect(transform, thumbnail).contains(cursor)
+                })
+                .last()
+                .map(|(ent, transform, _)| (ent, transform.translation.truncate()));
+            let Some((entity, corner)) = picked else {
+                return;
+            };
+            if mouse.just_pressed(MouseButton::Left) {
+                drag.0 = Some(WorldDrag::Move {
+                    entity,
+                    grab: cursor - corner,
+                });
+            } else if mouse.just_pressed(MouseButton::Right) {
+                drag.0 = Some(WorldDrag::Connect { entity });
+            }
+        }
+        Some(WorldDrag::Move { entity, grab }) => {
+            let Ok((_, mut transform, thumbnail)) = thumbnail_q.get_mut(entity) else {
+                drag.0 = None;
+                return;
+            };
+            let snapped = ((cursor - grab) / CELL).round() * CELL;
+            if transform.translation.truncate() != snapped {
+                transform.translation = snapped.extend(transform.translation.z);
+            }
+            if mouse.just_released(MouseButton::Left) {
+                let position = corner(&transform);
+                if let Some(level) = project.levels.get_mut(thumbnail.index) {
+                    level.position = Some([position.x, position.y]);
+                }
+                project.write();
+                drag.0 = None;
+            }
+        }
+        Some(WorldDrag::Connect { entity }) => {
+            if !mouse.just_released(MouseButton::Right) {
+                return;
+            }
+            drag.0 = None;
+            let Ok((_, from_transform, from)) = thumbnail_q.get(entity) else {
+                return;
+            };
+            let Some((_, to_transform, to)) =
+                thumbnail_q.iter().find(|(ent, transform, thumbnail)| {
+                    *ent != entity && thumbnail_rect(transform, thumbnail).contains(cursor)
+                })
+            else {
+                return;
+            };
+            let (Some(from_path), Some(to_path)) = (
+                project
+                    .levels
+                    .get(from.index)
+                    .map(|level| level.path.clone()),
+                project.levels.get(to.index).map(|level| level.path.clone()),
+            ) else {
+                return;
+            };
+            let connection = Connection {
+                from: from_path,
+                to: to_path,
+                edge: facing_edge(
+                    thumbnail_rect(from_transform, from),
+                    thumbnail_rect(to_transform, to),
+                ),
+            };
+            match project.connections.iter().position(|c| *c == connection) {
+                Some(existing) => {
+                    project.connections.remove(existing);
+                }
+                None => project.connections.push(connection),
+            }
+            project.write();
+        }
+    }
+}
+
+fn edge_midpoint(rect: Rect, edge: Edge) -> Vec2 {
+    match edge {
+        Edge::Left => Vec2::new(rect.min.x, rect.center().y),
+        Edge::Right => Vec2::new(rect.max.x, rect.center().y),
+        Edge::Top => Vec2::new(rect.center().x, rect.max.y),
+        Edge::Bottom => Vec2::new(rect.center().x, rect.min.y),
+    }
+}
+
+pub fn draw_world(
+    mut gizmos: Gizmos,
+    project: Res<Project>,
+    current_level: Res<CurrentLevel>,
+    drag: Res<WorldDragState>,
+    window_q: Query<&Window, With<PrimaryWindow>>,
+    cam_q: Query<(&Camera, &GlobalTransform)>,
+    thumbnail_q: Query<(Entity, &Transform, &LevelThumbnail)>,
+) {
+    for (_, transform, thumbnail) in &thumbnail_q {
+        let rect = thumbnail_rect(transform, thumbnail);
+        for cell in &thumbnail.cells {
+            let center = rect.min + (cell.as_vec2() + 0.5) * CELL;
+            gizmos.rect_2d(center, 0.0, Vec2::splat(CELL * 0.8), TILE_COLOR);
+        }
+        let open = project
+            .levels
+            .get(thumbnail.index)
+            .is_some_and(|level| Path::new(&level.path) == current_level.path);
+        let color = match open {
+            true => PRESSED_BORDER,
+            false => HOVER_BORDER,
+        };
+        gizmos.rect_2d(rect.center(), 0.0, rect.size(), color);
+    }
+
+    let rect_of = |path: &str| {
+        thumbnail_q.iter().find_map(|(_, transform, thumbnail)| {
+            let level = project.levels.get(thumbnail.index)?;
+            (level.path == path).then(|| thumbnail_rect(transform, thumbnail))
+        })
+    };
+    for connection in &project.connections {
+        let (Some(from), Some(to)) = (rect_of(&connection.from), rect_of(&connection.to)) else {
+            continue;
+        };
+        gizmos.arrow_2d(
+            edge_midpoint(from, connection.edge),
+            to.center(),
+            CONNECTION_COLOR,
+        );
+    }
+
+    // the connection being drawn follows the cursor
+    let Some(WorldDrag::Connect { entity }) = drag.0 else {
+        return;
+    };
+    let (Some(cursor), (camera, camera_transform)) =
+        (window_q.single().cursor_position(), cam_q.single())
+    else {
+        return;
+    };
+    let (Some(cursor), Ok((_, transform, thumbnail))) = (
+        camera.viewport_to_world_2d(camera_transform, cursor),
+        thumbnail_q.get(entity),
+    ) else {
+        return;
+    };
+    gizmos.arrow_2d(
+        thumbnail_rect(transform, thumbnail).center(),
+        cursor,
+        CONNECTION_COLOR.with_alpha(0.5),
+    );
+}